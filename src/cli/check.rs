@@ -0,0 +1,228 @@
+use crate::cli::cli::{Cli, OutputFormat};
+use crate::cli::table;
+use anyhow::{Context, Result};
+use pw::models::password_check::PasswordCheck;
+use pw::{Charset, Composition, PasswordAnalysis, analyze_password};
+use std::io::{self, BufRead};
+
+/// Analyze `passwords`, or every line of stdin when there are none, and print the results
+pub fn run(cli: &Cli, passwords: &[String]) -> Result<()> {
+    let passwords = if passwords.is_empty() {
+        io::stdin()
+            .lock()
+            .lines()
+            .collect::<io::Result<Vec<String>>>()
+            .context("Failed to read passwords from stdin")?
+            .into_iter()
+            .filter(|line| !line.is_empty())
+            .collect()
+    } else {
+        passwords.to_vec()
+    };
+
+    let charset = Charset::from_options(
+        &cli.preset,
+        cli.symbols.as_deref(),
+        cli.exclude_chars.as_deref(),
+    )?;
+    let has_policy = cli.policy.is_some() || cli.profile.is_some();
+
+    let checks: Vec<PasswordCheck> = passwords
+        .iter()
+        .map(|password| {
+            let analysis = analyze_password(password, &charset);
+            let policy_violations = if has_policy {
+                policy_violations(cli, &charset, &analysis)
+            } else {
+                Vec::new()
+            };
+
+            PasswordCheck {
+                matches_policy: has_policy.then_some(policy_violations.is_empty()),
+                policy_violations,
+                analysis,
+            }
+        })
+        .collect();
+
+    match cli.output {
+        OutputFormat::Text => {
+            for check in &checks {
+                print_check(check);
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&checks)?),
+        OutputFormat::Ndjson => {
+            for check in &checks {
+                println!("{}", serde_json::to_string(check)?);
+            }
+        }
+        OutputFormat::Yaml => println!("{}", serde_yml::to_string(&checks)?),
+        OutputFormat::Csv | OutputFormat::Tsv => write_table(cli, &checks, has_policy)?,
+    }
+
+    Ok(())
+}
+
+// One row per password, the segments are left out as the pattern already describes them
+fn write_table(cli: &Cli, checks: &[PasswordCheck], has_policy: bool) -> Result<()> {
+    let mut writer = table::writer(&cli.output);
+
+    let mut header = vec![
+        "password",
+        "length",
+        "uppercase",
+        "lowercase",
+        "digits",
+        "symbols",
+        "entropy_bits",
+        "pattern",
+    ];
+    if has_policy {
+        header.extend(["matches_policy", "policy_violations"]);
+    }
+    table::write_record(&mut writer, header)?;
+
+    for check in checks {
+        let analysis = &check.analysis;
+        let mut row = vec![
+            analysis.password.clone(),
+            analysis.length.to_string(),
+            analysis.uppercase.to_string(),
+            analysis.lowercase.to_string(),
+            analysis.digits.to_string(),
+            analysis.symbols.to_string(),
+            analysis.entropy_bits.to_string(),
+            analysis.pattern.clone(),
+        ];
+        if has_policy {
+            row.push(check.policy_violations.is_empty().to_string());
+            row.push(check.policy_violations.join("; "));
+        }
+        table::write_record(&mut writer, row)?;
+    }
+
+    table::flush(&mut writer)
+}
+
+fn print_check(check: &PasswordCheck) {
+    let analysis = &check.analysis;
+    let classes = Composition {
+        upper: analysis.uppercase,
+        lower: analysis.lowercase,
+        digit: analysis.digits,
+        symbol: analysis.symbols,
+    };
+    let segments: Vec<String> = analysis
+        .segments
+        .iter()
+        .map(|s| format!("{:?}", s.text))
+        .collect();
+
+    println!("{}", analysis.password);
+    println!("  Length:  {}", analysis.length);
+    println!("  Classes: {}", classes.describe());
+    println!("  Entropy: {} bits", analysis.entropy_bits);
+    println!("  Pattern: {} ({})", analysis.pattern, segments.join(" "));
+    match check.matches_policy {
+        Some(true) => println!("  Policy:  matches"),
+        Some(false) => {
+            println!("  Policy:  does not match");
+            for violation in &check.policy_violations {
+                println!("    - {}", violation);
+            }
+        }
+        None => {}
+    }
+}
+
+// The rules of the effective settings an existing password breaks
+fn policy_violations(cli: &Cli, charset: &Charset, analysis: &PasswordAnalysis) -> Vec<String> {
+    let mut violations = Vec::new();
+    let password = &analysis.password;
+
+    // Lengths bound the words of patterns and passphrases, not the whole password
+    if cli.pattern.is_none() && cli.passphrase.is_none() {
+        let (min_length, max_length) = match cli.length {
+            Some(length) => (Some(length), Some(length)),
+            None => (cli.min_length, cli.max_length),
+        };
+        if min_length.is_some_and(|min| analysis.length < min) {
+            violations.push(format!(
+                "Shorter than {} characters",
+                min_length.unwrap_or_default()
+            ));
+        }
+        if max_length.is_some_and(|max| analysis.length > max) {
+            violations.push(format!(
+                "Longer than {} characters",
+                max_length.unwrap_or_default()
+            ));
+        }
+    }
+
+    if let Some(c) = password.chars().find(|c| !charset.allows(*c)) {
+        violations.push(format!("Contains the excluded character '{}'", c));
+    }
+
+    let restricts_symbols =
+        cli.symbols.is_some() || cli.preset.iter().any(|preset| preset.symbols().is_some());
+    if restricts_symbols
+        && let Some(c) = password
+            .chars()
+            .find(|c| !c.is_alphanumeric() && !c.is_whitespace() && !charset.symbols().contains(c))
+    {
+        violations.push(format!("Contains '{}' which is not an allowed symbol", c));
+    }
+
+    let requirements = [
+        (
+            Composition {
+                upper: cli.require_upper,
+                ..Composition::default()
+            },
+            analysis.uppercase,
+        ),
+        (
+            Composition {
+                lower: cli.require_lower,
+                ..Composition::default()
+            },
+            analysis.lowercase,
+        ),
+        (
+            Composition {
+                digit: cli.require_digit,
+                ..Composition::default()
+            },
+            analysis.digits,
+        ),
+        (
+            Composition {
+                symbol: cli.require_symbol,
+                ..Composition::default()
+            },
+            analysis.symbols,
+        ),
+    ];
+    for (required, found) in requirements {
+        if !required.is_empty() && found < required.total() {
+            violations.push(format!(
+                "Needs at least {}, has {}",
+                required.describe(),
+                found
+            ));
+        }
+    }
+
+    if let Some(min_entropy) = cli.min_entropy
+        && analysis.entropy_bits < min_entropy
+    {
+        violations.push(format!(
+            "Estimated entropy of {} bits is below {} bits",
+            analysis.entropy_bits, min_entropy
+        ));
+    }
+
+    violations
+}
@@ -1,13 +1,7 @@
-use clap::{Parser, ValueEnum};
-#[derive(Debug, Clone, ValueEnum)]
-pub enum EncodingFormat {
-    None,
-    Base64,
-    Url,
-    Sha256,
-    Sha512,
-    Htpasswd,
-}
+use clap::{Parser, Subcommand};
+use pw::{CharsetPreset, EncodingFormat, Kdf};
+use serde::{Deserialize, Serialize};
+
 #[derive(Parser, Debug)]
 #[command(
     version,
@@ -21,55 +15,245 @@ pub struct Cli {
     /// Number of passwords to generate. This will create N number of random passwords
     #[arg(default_value = "1")]
     pub number_of_passwords: usize,
-    
+
     /// Set a fixed length instead of using -m and -M
-    #[arg(short = 'L', long)]
+    #[arg(short = 'L', long, global = true)]
     pub length: Option<usize>,
 
     /// Force lowercase characters on generated passwords
-    #[arg(short = 'l', long, default_value = "false")]
+    #[arg(short = 'l', long, default_value = "false", global = true)]
     pub lowercase: bool,
-    
+
     /// Generate a completely random password
-    #[arg(short = 'R', long, default_value = "false")]
+    #[arg(short = 'R', long, default_value = "false", global = true)]
     pub random: bool,
-    
+
     /// Minimum word length
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub min_length: Option<usize>,
 
     /// Maximum word length
-    #[arg(short = 'M', long)]
+    #[arg(short = 'M', long, global = true)]
     pub max_length: Option<usize>,
 
     /// Output format
-    #[arg(short = 'O', long = "output", default_value = "text")]
-    pub format: OutputFormat,
+    #[arg(short = 'O', long, default_value = "text", global = true)]
+    pub output: OutputFormat,
 
-    /// Use seed for deterministic output, will be deterministic for multiple passwords
-    #[arg(short, long)]
+    /// Use seed for deterministic output, will be deterministic for multiple passwords.
+    /// Seeded passwords have at most 64 bits of randomness, don't use them as real passwords
+    #[arg(short, long, global = true)]
     pub seed: Option<u64>,
 
     /// Force capitalization of generated words
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub capitalize: bool,
 
-    /// Will print the seed
-    #[arg(short, long)]
+    /// Never repeat a password within the batch, fails when the options allow too few passwords
+    #[arg(long, global = true)]
+    pub unique: bool,
+
+    /// Will print the seed (with --seed) and the estimated entropy of each password
+    #[arg(short, long, global = true)]
     pub verbose: bool,
     /// Pattern for password generation (W=Word, N=Number, C/S=Special Character). Use single or double quotes for fixed characters.
+    /// U, L, D, H, V and K are an uppercase letter, lowercase letter, digit, hex digit, vowel and consonant,
+    /// [abc123] one character of the set. W{2} and C{3} repeat a token, N{4} or N{2,4} set the digit count and W[4-6] the word length.
+    /// (W|N) picks one of the alternatives and W? or ('-'W)? is optional. W^, W_, W~ and W% make a token UPPER, lower, Capitalized or random case.
     /// Example: "'cool-'W-'cool'" will print cool-RANDOM_WORD-cool
-    #[arg(short = 'p', long)]
+    #[arg(short = 'p', long, global = true)]
     pub pattern: Option<String>,
 
-    /// Encode the output password. htpasswd = slow by nature
-    #[arg(short = 'e', long, value_enum, default_value = "none")]
-    pub encode: EncodingFormat,
+    /// Reject letters in patterns that are no token instead of using them as literal text, quote literal text instead.
+    /// Also warns about casing modifiers such as the _ of W_N, which used to be literal text
+    #[arg(long, global = true)]
+    pub strict_pattern: bool,
+
+    /// Generate a passphrase of this many words instead of a password. Word length is controlled by -L, -m and -M
+    #[arg(short = 'P', long, value_name = "COUNT", global = true)]
+    pub passphrase: Option<usize>,
+
+    /// Separator between the words of a passphrase
+    #[arg(long, default_value = "-", global = true)]
+    pub separator: String,
+
+    /// Insert a random digit into one of the words of a passphrase
+    #[arg(long, global = true)]
+    pub inject_digit: bool,
+
+    /// Insert a random special character into one of the words of a passphrase
+    #[arg(long, global = true)]
+    pub inject_symbol: bool,
+
+    /// Draw the words of patterns and passphrases from a wordlist: a file with one word per line, or the built-in eff-large / eff-short lists
+    #[arg(long, value_name = "PATH", global = true)]
+    pub wordlist: Option<String>,
+
+    /// Special characters to use instead of the default set "!@#$%^&*()-_=+[]{}|;:,.<>?~"
+    #[arg(long, value_name = "SET", global = true)]
+    pub symbols: Option<String>,
+
+    /// Characters that must never appear in a password
+    #[arg(long, value_name = "SET", global = true)]
+    pub exclude_chars: Option<String>,
+
+    /// Named character restrictions, can be repeated. --symbols replaces the symbols of a preset
+    #[arg(long, value_enum, value_name = "PRESET", global = true)]
+    pub preset: Vec<CharsetPreset>,
+
+    /// Require at least this many uppercase letters
+    #[arg(long, value_name = "N", default_value = "0", global = true)]
+    pub require_upper: usize,
+
+    /// Require at least this many lowercase letters
+    #[arg(long, value_name = "N", default_value = "0", global = true)]
+    pub require_lower: usize,
+
+    /// Require at least this many digits
+    #[arg(long, value_name = "N", default_value = "0", global = true)]
+    pub require_digit: usize,
+
+    /// Require at least this many special characters
+    #[arg(long, value_name = "N", default_value = "0", global = true)]
+    pub require_symbol: usize,
+
+    /// Reject passwords with less estimated entropy than this many bits and generate new ones
+    #[arg(long, value_name = "BITS", global = true)]
+    pub min_entropy: Option<f64>,
+
+    /// Read generation rules from a YAML or JSON policy file, flags on the command line take precedence
+    #[arg(long, value_name = "FILE", global = true)]
+    pub policy: Option<String>,
+
+    /// Use the settings of a profile from the config file (~/.config/pw/config.toml)
+    #[arg(long, value_name = "NAME", global = true)]
+    pub profile: Option<String>,
+
+    /// Argon2id memory cost in KiB
+    #[arg(long, value_name = "KIB", default_value = "19456", global = true)]
+    pub argon2_memory: u32,
+
+    /// Argon2id number of iterations
+    #[arg(long, value_name = "N", default_value = "2", global = true)]
+    pub argon2_iterations: u32,
+
+    /// Argon2id degree of parallelism
+    #[arg(long, value_name = "N", default_value = "1", global = true)]
+    pub argon2_parallelism: u32,
+
+    /// scrypt CPU/memory cost as a power of two, N = 2^LOG_N
+    #[arg(long, value_name = "LOG_N", default_value = "17", global = true)]
+    pub scrypt_log_n: u8,
+
+    /// scrypt block size
+    #[arg(long, value_name = "R", default_value = "8", global = true)]
+    pub scrypt_r: u32,
+
+    /// scrypt parallelization
+    #[arg(long, value_name = "P", default_value = "1", global = true)]
+    pub scrypt_p: u32,
+
+    /// PBKDF2-HMAC-SHA256 iterations
+    #[arg(long, value_name = "N", default_value = "600000", global = true)]
+    pub pbkdf2_rounds: u32,
+
+    /// sha256-crypt and sha512-crypt rounds
+    #[arg(long, value_name = "N", default_value = "5000", global = true)]
+    pub crypt_rounds: u32,
+
+    /// bcrypt cost of the htpasswd encoding, from 4 to 31
+    #[arg(long, value_name = "COST", default_value = "10", global = true)]
+    pub bcrypt_cost: u32,
+
+    /// Output full `user:hash` htpasswd entries. Requires an htpasswd encoding
+    #[arg(long, global = true)]
+    pub username: Option<String>,
+
+    /// Encode the output password, repeat for several encodings of the same password.
+    /// htpasswd and the crypt/PHC hashes are slow by nature
+    #[arg(short = 'e', long, value_enum, global = true)]
+    pub encode: Vec<EncodingFormat>,
+
+    /// Text output template: env, k8s-secret, docker-secret or a template such as
+    /// '{password}\t{sha256}'. Encodings used in the template are computed without -e
+    #[arg(long, global = true)]
+    pub template: Option<String>,
+
+    /// Name of the variable or secret in templates, numbered when there are several passwords
+    #[arg(long, default_value = "password", global = true)]
+    pub name: String,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Analyze existing passwords given as arguments or one per line on stdin.
+    /// They are checked against the rules of --policy or --profile when one is given
+    Check {
+        /// Passwords to check, read from stdin when none are given
+        passwords: Vec<String>,
+    },
+
+    /// Inspect the configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
+
+    /// Inspect patterns for -p
+    #[command(subcommand)]
+    Pattern(PatternCommand),
+
+    /// Derive a password from a master passphrase, read from the terminal, and a site name.
+    /// The same inputs and generation options always derive the same password
+    Derive {
+        /// Site or service the password is for, e.g. example.com
+        #[arg(long)]
+        site: String,
+
+        /// User name on the site
+        #[arg(long, default_value = "")]
+        user: String,
+
+        /// Increase to derive a new password for the same site and user
+        #[arg(long, default_value = "1")]
+        counter: u32,
+
+        /// Key derivation function used to stretch the master passphrase
+        #[arg(long, value_enum, default_value = "argon2id")]
+        kdf: Kdf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print the effective settings and where each of them comes from
+    Show,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PatternCommand {
+    /// Show what each token of a pattern produces, the length and entropy of the passwords and
+    /// likely mistakes. The generation options such as -L, --wordlist and --exclude-chars apply
+    Explain {
+        /// The pattern, as given to -p
+        #[arg(value_name = "PATTERN")]
+        expression: String,
+    },
 }
 
-#[derive(Debug, Clone, clap::ValueEnum)]
+#[derive(Debug, Clone, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum OutputFormat {
     Text,
     Yaml,
-    Json
+    Json,
+    /// One JSON object per line, also known as JSON Lines
+    #[value(alias = "jsonl")]
+    #[serde(alias = "jsonl")]
+    Ndjson,
+    /// Comma separated values with a header row
+    Csv,
+    /// Tab separated values with a header row
+    Tsv,
 }
@@ -0,0 +1,113 @@
+use crate::cli::cli::Cli;
+use crate::cli::policy::{Policy, Sources, from_command_line};
+use anyhow::{Context, Result, anyhow};
+use clap::{ArgMatches, FromArgMatches};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Defaults and named profiles, read from `~/.config/pw/config.toml`.
+///
+/// ```toml
+/// [defaults]
+/// min_entropy = 60
+///
+/// [profiles.aws-iam]
+/// length = 32
+/// random = true
+/// require_symbol = 1
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub defaults: Policy,
+    pub profiles: BTreeMap<String, Policy>,
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/pw/config.toml`, falling back to `~/.config/pw/config.toml`
+    pub fn path() -> Option<PathBuf> {
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+        Some(config_dir.join("pw").join("config.toml"))
+    }
+
+    /// Load the config file, an empty config when there is none
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::path().filter(|path| path.exists()) else {
+            return Ok(Self::default());
+        };
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("Invalid config {}", path.display()))
+    }
+}
+
+/// Build the effective settings: config defaults, then the selected profile, then the policy
+/// file and finally the flags given on the command line
+pub fn resolve(matches: &ArgMatches) -> Result<(Cli, Sources)> {
+    let mut cli = Cli::from_arg_matches(matches)?;
+    let mut sources = Sources::new();
+    let mut config = Config::load()?;
+
+    let defaults = std::mem::take(&mut config.defaults);
+    defaults.apply(&mut cli, matches, "config", &mut sources);
+
+    if let Some(name) = cli.profile.clone() {
+        let profile = config.profiles.remove(&name).ok_or_else(|| {
+            let available: Vec<&str> = config.profiles.keys().map(String::as_str).collect();
+            anyhow!(
+                "Unknown profile '{}', available profiles: {}",
+                name,
+                if available.is_empty() {
+                    "none".to_string()
+                } else {
+                    available.join(", ")
+                }
+            )
+        })?;
+        profile.apply(
+            &mut cli,
+            matches,
+            &format!("profile {}", name),
+            &mut sources,
+        );
+    }
+
+    if let Some(path) = cli.policy.clone() {
+        Policy::load(&path)?.apply(&mut cli, matches, &format!("policy {}", path), &mut sources);
+    }
+
+    for id in matches.ids() {
+        if from_command_line(matches, id.as_str()) {
+            sources.insert(id.to_string(), "command line".to_string());
+        }
+    }
+
+    Ok((cli, sources))
+}
+
+/// Print every setting that has a value together with where it came from
+pub fn show(cli: &Cli, sources: &Sources) -> Result<()> {
+    match Config::path() {
+        Some(path) if path.exists() => println!("# Config: {}", path.display()),
+        Some(path) => println!("# Config: {} (not found)", path.display()),
+        None => println!("# Config: none"),
+    }
+
+    let settings = toml::to_string(&Policy::from_cli(cli))?;
+    let width = settings.lines().map(str::len).max().unwrap_or_default();
+    for line in settings.lines() {
+        let key = line.split_once(" = ").map_or(line, |(key, _)| key);
+        let source = sources.get(key).map_or("default", String::as_str);
+        println!("{:width$}  # {}", line, source, width = width);
+    }
+
+    Ok(())
+}
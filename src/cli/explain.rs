@@ -0,0 +1,141 @@
+use crate::cli::cli::{Cli, OutputFormat};
+use crate::cli::table;
+use anyhow::Result;
+use pw::PasswordGenerator;
+use pw::generator::entropy::round_bits;
+use pw::models::pattern_explanation::{Bounds, PatternExplanation, TokenExplanation};
+
+/// Print what the pattern of `generator` produces
+pub fn run(cli: &Cli, generator: &PasswordGenerator) -> Result<()> {
+    let mut explanation = generator.explain_pattern()?;
+    explanation.bounds = rounded(explanation.bounds);
+    explanation.tokens.iter_mut().for_each(round_token);
+
+    match cli.output {
+        OutputFormat::Text => print_explanation(&explanation),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&explanation)?),
+        OutputFormat::Ndjson => println!("{}", serde_json::to_string(&explanation)?),
+        OutputFormat::Yaml => println!("{}", serde_yml::to_string(&explanation)?),
+        OutputFormat::Csv | OutputFormat::Tsv => write_table(cli, &explanation)?,
+    }
+
+    Ok(())
+}
+
+fn rounded(bounds: Bounds) -> Bounds {
+    Bounds {
+        min_entropy_bits: round_bits(bounds.min_entropy_bits),
+        max_entropy_bits: round_bits(bounds.max_entropy_bits),
+        ..bounds
+    }
+}
+
+fn round_token(token: &mut TokenExplanation) {
+    token.bounds = rounded(token.bounds);
+    token.children.iter_mut().for_each(round_token);
+}
+
+// Every token and the tokens nested in groups in order, with how deep they are nested
+fn flatten(tokens: &[TokenExplanation], depth: usize) -> Vec<(usize, &TokenExplanation)> {
+    tokens
+        .iter()
+        .flat_map(|token| {
+            let mut rows = vec![(depth, token)];
+            rows.extend(flatten(&token.children, depth + 1));
+            rows
+        })
+        .collect()
+}
+
+// One row per token, the warnings are left out
+fn write_table(cli: &Cli, explanation: &PatternExplanation) -> Result<()> {
+    let mut writer = table::writer(&cli.output);
+
+    table::write_record(
+        &mut writer,
+        [
+            "token",
+            "depth",
+            "output",
+            "min_length",
+            "max_length",
+            "min_entropy_bits",
+            "max_entropy_bits",
+        ],
+    )?;
+
+    for (depth, token) in flatten(&explanation.tokens, 0) {
+        table::write_record(
+            &mut writer,
+            [
+                token.token.clone(),
+                depth.to_string(),
+                token.output.clone(),
+                token.bounds.min_length.to_string(),
+                token.bounds.max_length.to_string(),
+                token.bounds.min_entropy_bits.to_string(),
+                token.bounds.max_entropy_bits.to_string(),
+            ],
+        )?;
+    }
+
+    table::flush(&mut writer)
+}
+
+fn print_explanation(explanation: &PatternExplanation) {
+    let rows: Vec<[String; 4]> = flatten(&explanation.tokens, 0)
+        .into_iter()
+        .map(|(depth, token)| {
+            [
+                format!("{}{}", "  ".repeat(depth), token.token),
+                token.output.clone(),
+                format!(
+                    "{} {}",
+                    length_range(token.bounds),
+                    if token.bounds.max_length == 1 {
+                        "char"
+                    } else {
+                        "chars"
+                    }
+                ),
+                format!("{} bits", entropy_range(token.bounds)),
+            ]
+        })
+        .collect();
+    let width = |column: usize| {
+        rows.iter()
+            .map(|row| row[column].chars().count())
+            .max()
+            .unwrap_or_default()
+    };
+    let (token_width, output_width, length_width) = (width(0), width(1), width(2));
+
+    println!("{}", explanation.pattern);
+    for [token, output, length, entropy] in &rows {
+        println!(
+            "  {:token_width$}  {:output_width$}  {:>length_width$}  {}",
+            token, output, length, entropy
+        );
+    }
+    println!("  Length:  {} characters", length_range(explanation.bounds));
+    println!("  Entropy: {} bits", entropy_range(explanation.bounds));
+    for warning in &explanation.warnings {
+        println!("  Warning: {}", warning);
+    }
+}
+
+fn length_range(bounds: Bounds) -> String {
+    if bounds.min_length == bounds.max_length {
+        bounds.min_length.to_string()
+    } else {
+        format!("{} to {}", bounds.min_length, bounds.max_length)
+    }
+}
+
+fn entropy_range(bounds: Bounds) -> String {
+    if bounds.min_entropy_bits == bounds.max_entropy_bits {
+        bounds.min_entropy_bits.to_string()
+    } else {
+        format!("{} to {}", bounds.min_entropy_bits, bounds.max_entropy_bits)
+    }
+}
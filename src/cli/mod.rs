@@ -1 +1,9 @@
-pub mod cli;
\ No newline at end of file
+pub mod check;
+#[allow(clippy::module_inception)]
+pub mod cli;
+pub mod config;
+pub mod explain;
+pub mod output;
+pub mod policy;
+pub mod table;
+pub mod template;
@@ -0,0 +1,84 @@
+use crate::cli::cli::OutputFormat;
+use crate::cli::table::PasswordTable;
+use anyhow::{Context, Result};
+use pw::EncodingFormat;
+use pw::models::password_output::PasswordOutput;
+use std::io::{self, Write};
+use std::process;
+
+/// Writes structured output one password at a time, so batches of any size start printing right
+/// away and use constant memory. The JSON and YAML documents are the same as serializing every
+/// password at once
+pub enum PasswordWriter {
+    Json { count: usize },
+    Ndjson,
+    Yaml { count: usize },
+    Table(Box<PasswordTable>),
+}
+
+impl PasswordWriter {
+    /// Writer for `output`, `None` for text output. The remaining arguments lay out csv and tsv
+    /// tables, see [`PasswordTable::new`]
+    pub fn new(
+        output: &OutputFormat,
+        encodings: &[EncodingFormat],
+        hash_params: Vec<(&'static str, &'static str)>,
+        username: bool,
+    ) -> Result<Option<Self>> {
+        let writer = match output {
+            OutputFormat::Text => return Ok(None),
+            OutputFormat::Json => PasswordWriter::Json { count: 0 },
+            OutputFormat::Ndjson => PasswordWriter::Ndjson,
+            OutputFormat::Yaml => PasswordWriter::Yaml { count: 0 },
+            OutputFormat::Csv | OutputFormat::Tsv => PasswordWriter::Table(Box::new(
+                PasswordTable::new(output, encodings, hash_params, username)?,
+            )),
+        };
+
+        Ok(Some(writer))
+    }
+
+    /// Write the password numbered `index`, counting from 1
+    pub fn write(&mut self, index: usize, output: &PasswordOutput) -> Result<()> {
+        match self {
+            PasswordWriter::Json { count } => {
+                // Indent the object as an element of the pretty printed array
+                let json = serde_json::to_string_pretty(output)?.replace('\n', "\n  ");
+                let separator = if *count == 0 { "[\n" } else { ",\n" };
+                *count += 1;
+                print(&format!("{}  {}", separator, json))
+            }
+            PasswordWriter::Ndjson => print(&format!("{}\n", serde_json::to_string(output)?)),
+            PasswordWriter::Yaml { count } => {
+                *count += 1;
+                print(&serde_yml::to_string(&[output])?)
+            }
+            PasswordWriter::Table(table) => table.row(index, output),
+        }
+    }
+
+    /// Close the document
+    pub fn finish(self) -> Result<()> {
+        match self {
+            PasswordWriter::Json { count: 0 } => print("[]\n"),
+            PasswordWriter::Json { .. } => print("\n]\n"),
+            PasswordWriter::Ndjson => Ok(()),
+            PasswordWriter::Yaml { count: 0 } => print("[]\n\n"),
+            PasswordWriter::Yaml { .. } => print("\n"),
+            PasswordWriter::Table(table) => table.finish(),
+        }
+    }
+}
+
+// Flushed right away so every password shows up as soon as it is generated. A closed pipe, as
+// with `pw 1000000 -O ndjson | head`, ends the program like it ends any other stream filter
+pub fn print(text: &str) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    match stdout
+        .write_all(text.as_bytes())
+        .and_then(|()| stdout.flush())
+    {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => process::exit(0),
+        result => result.context("Failed to write output"),
+    }
+}
@@ -0,0 +1,270 @@
+use crate::cli::cli::{Cli, OutputFormat};
+use anyhow::{Context, Result};
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+use pw::{CharsetPreset, EncodingFormat};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Generation rules for a target system, read from a YAML or JSON policy file or a config profile.
+/// Every field is optional and named after the command line flag it replaces.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    pub length: Option<usize>,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub random: Option<bool>,
+    pub pattern: Option<String>,
+    pub strict_pattern: Option<bool>,
+    pub passphrase: Option<usize>,
+    pub separator: Option<String>,
+    pub wordlist: Option<String>,
+    pub capitalize: Option<bool>,
+    pub lowercase: Option<bool>,
+    pub inject_digit: Option<bool>,
+    pub inject_symbol: Option<bool>,
+    pub symbols: Option<String>,
+    pub exclude_chars: Option<String>,
+    pub preset: Option<Vec<CharsetPreset>>,
+    pub require_upper: Option<usize>,
+    pub require_lower: Option<usize>,
+    pub require_digit: Option<usize>,
+    pub require_symbol: Option<usize>,
+    pub min_entropy: Option<f64>,
+    pub unique: Option<bool>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub encode: Option<Vec<EncodingFormat>>,
+    pub argon2_memory: Option<u32>,
+    pub argon2_iterations: Option<u32>,
+    pub argon2_parallelism: Option<u32>,
+    pub scrypt_log_n: Option<u8>,
+    pub scrypt_r: Option<u32>,
+    pub scrypt_p: Option<u32>,
+    pub pbkdf2_rounds: Option<u32>,
+    pub crypt_rounds: Option<u32>,
+    pub bcrypt_cost: Option<u32>,
+    pub output: Option<OutputFormat>,
+    pub template: Option<String>,
+    pub name: Option<String>,
+}
+
+impl Policy {
+    /// Load a policy, files ending in `.json` are read as JSON and everything else as YAML
+    pub fn load(path: &str) -> Result<Self> {
+        let content =
+            fs::read_to_string(path).with_context(|| format!("Failed to read policy {}", path))?;
+
+        let is_json = Path::new(path)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+        let policy = if is_json {
+            serde_json::from_str(&content).map_err(anyhow::Error::from)
+        } else {
+            serde_yml::from_str(&content).map_err(anyhow::Error::from)
+        };
+
+        policy.with_context(|| format!("Invalid policy {}", path))
+    }
+
+    /// Apply the policy to `cli` on top of the earlier layers, keeping every value that was
+    /// given on the command line. `source` is recorded for every setting the policy changes
+    pub fn apply(
+        mut self,
+        cli: &mut Cli,
+        matches: &ArgMatches,
+        source: &str,
+        sources: &mut Sources,
+    ) {
+        let given = |id: &str| from_command_line(matches, id);
+
+        // A length or mode on the command line replaces the one of the policy as a whole
+        if given("length") {
+            self.min_length = None;
+            self.max_length = None;
+        }
+        if given("min_length") || given("max_length") {
+            self.length = None;
+        }
+        if given("random") || given("pattern") || given("passphrase") {
+            self.random = None;
+            self.pattern = None;
+            self.passphrase = None;
+        }
+
+        // Just like a length or mode of the policy replaces the one of an earlier layer
+        if self.length.is_some() {
+            cli.min_length = None;
+            cli.max_length = None;
+            sources.remove("min_length");
+            sources.remove("max_length");
+        }
+        if self.min_length.is_some() || self.max_length.is_some() {
+            cli.length = None;
+            sources.remove("length");
+        }
+        if self.random.is_some() || self.pattern.is_some() || self.passphrase.is_some() {
+            cli.random = false;
+            cli.pattern = None;
+            cli.passphrase = None;
+            for id in ["random", "pattern", "passphrase"] {
+                sources.remove(id);
+            }
+        }
+
+        let mut layer = Layer {
+            matches,
+            source,
+            sources,
+        };
+        layer.set("length", &mut cli.length, self.length.map(Some));
+        layer.set("min_length", &mut cli.min_length, self.min_length.map(Some));
+        layer.set("max_length", &mut cli.max_length, self.max_length.map(Some));
+        layer.set("random", &mut cli.random, self.random);
+        layer.set("pattern", &mut cli.pattern, self.pattern.map(Some));
+        layer.set(
+            "strict_pattern",
+            &mut cli.strict_pattern,
+            self.strict_pattern,
+        );
+        layer.set("passphrase", &mut cli.passphrase, self.passphrase.map(Some));
+        layer.set("separator", &mut cli.separator, self.separator);
+        layer.set("wordlist", &mut cli.wordlist, self.wordlist.map(Some));
+        layer.set("capitalize", &mut cli.capitalize, self.capitalize);
+        layer.set("lowercase", &mut cli.lowercase, self.lowercase);
+        layer.set("inject_digit", &mut cli.inject_digit, self.inject_digit);
+        layer.set("inject_symbol", &mut cli.inject_symbol, self.inject_symbol);
+        layer.set("symbols", &mut cli.symbols, self.symbols.map(Some));
+        layer.set(
+            "exclude_chars",
+            &mut cli.exclude_chars,
+            self.exclude_chars.map(Some),
+        );
+        layer.set("preset", &mut cli.preset, self.preset);
+        layer.set("require_upper", &mut cli.require_upper, self.require_upper);
+        layer.set("require_lower", &mut cli.require_lower, self.require_lower);
+        layer.set("require_digit", &mut cli.require_digit, self.require_digit);
+        layer.set(
+            "require_symbol",
+            &mut cli.require_symbol,
+            self.require_symbol,
+        );
+        layer.set(
+            "min_entropy",
+            &mut cli.min_entropy,
+            self.min_entropy.map(Some),
+        );
+        layer.set("unique", &mut cli.unique, self.unique);
+        layer.set("encode", &mut cli.encode, self.encode);
+        layer.set("argon2_memory", &mut cli.argon2_memory, self.argon2_memory);
+        layer.set(
+            "argon2_iterations",
+            &mut cli.argon2_iterations,
+            self.argon2_iterations,
+        );
+        layer.set(
+            "argon2_parallelism",
+            &mut cli.argon2_parallelism,
+            self.argon2_parallelism,
+        );
+        layer.set("scrypt_log_n", &mut cli.scrypt_log_n, self.scrypt_log_n);
+        layer.set("scrypt_r", &mut cli.scrypt_r, self.scrypt_r);
+        layer.set("scrypt_p", &mut cli.scrypt_p, self.scrypt_p);
+        layer.set("pbkdf2_rounds", &mut cli.pbkdf2_rounds, self.pbkdf2_rounds);
+        layer.set("crypt_rounds", &mut cli.crypt_rounds, self.crypt_rounds);
+        layer.set("bcrypt_cost", &mut cli.bcrypt_cost, self.bcrypt_cost);
+        layer.set("output", &mut cli.output, self.output);
+        layer.set("template", &mut cli.template, self.template.map(Some));
+        layer.set("name", &mut cli.name, self.name);
+    }
+
+    /// The settings `cli` ends up with, in the same shape as a policy file
+    pub fn from_cli(cli: &Cli) -> Self {
+        Self {
+            length: cli.length,
+            min_length: cli.min_length,
+            max_length: cli.max_length,
+            random: Some(cli.random),
+            pattern: cli.pattern.clone(),
+            strict_pattern: Some(cli.strict_pattern),
+            passphrase: cli.passphrase,
+            separator: Some(cli.separator.clone()),
+            wordlist: cli.wordlist.clone(),
+            capitalize: Some(cli.capitalize),
+            lowercase: Some(cli.lowercase),
+            inject_digit: Some(cli.inject_digit),
+            inject_symbol: Some(cli.inject_symbol),
+            symbols: cli.symbols.clone(),
+            exclude_chars: cli.exclude_chars.clone(),
+            preset: Some(cli.preset.clone()),
+            require_upper: Some(cli.require_upper),
+            require_lower: Some(cli.require_lower),
+            require_digit: Some(cli.require_digit),
+            require_symbol: Some(cli.require_symbol),
+            min_entropy: cli.min_entropy,
+            unique: Some(cli.unique),
+            encode: Some(cli.encode.clone()),
+            argon2_memory: Some(cli.argon2_memory),
+            argon2_iterations: Some(cli.argon2_iterations),
+            argon2_parallelism: Some(cli.argon2_parallelism),
+            scrypt_log_n: Some(cli.scrypt_log_n),
+            scrypt_r: Some(cli.scrypt_r),
+            scrypt_p: Some(cli.scrypt_p),
+            pbkdf2_rounds: Some(cli.pbkdf2_rounds),
+            crypt_rounds: Some(cli.crypt_rounds),
+            bcrypt_cost: Some(cli.bcrypt_cost),
+            output: Some(cli.output.clone()),
+            template: cli.template.clone(),
+            name: Some(cli.name.clone()),
+        }
+    }
+}
+
+// `encode` used to take a single encoding, policies written back then still load
+fn one_or_many<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<Vec<EncodingFormat>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(EncodingFormat),
+        Many(Vec<EncodingFormat>),
+    }
+
+    Ok(
+        Option::<OneOrMany>::deserialize(deserializer)?.map(|encode| match encode {
+            OneOrMany::One(format) => vec![format],
+            OneOrMany::Many(formats) => formats,
+        }),
+    )
+}
+
+/// Where each setting came from, keyed by setting name. Missing settings are defaults
+pub type Sources = BTreeMap<String, String>;
+
+pub fn from_command_line(matches: &ArgMatches, id: &str) -> bool {
+    matches.value_source(id) == Some(ValueSource::CommandLine)
+}
+
+struct Layer<'a> {
+    matches: &'a ArgMatches,
+    source: &'a str,
+    sources: &'a mut Sources,
+}
+
+impl Layer<'_> {
+    // Flags given on the command line always win over policies and profiles
+    fn set<T>(&mut self, id: &str, target: &mut T, value: Option<T>) {
+        if let Some(value) = value
+            && !from_command_line(self.matches, id)
+        {
+            *target = value;
+            self.sources.insert(id.to_string(), self.source.to_string());
+        }
+    }
+}
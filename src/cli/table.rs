@@ -0,0 +1,113 @@
+use crate::cli::cli::OutputFormat;
+use anyhow::{Context, Result};
+use csv::{Writer, WriterBuilder};
+use pw::EncodingFormat;
+use pw::models::password_output::PasswordOutput;
+use std::io::{self, Stdout};
+use std::process;
+
+/// csv or tsv writer on stdout. Fields holding the delimiter, quotes or newlines are quoted
+pub fn writer(output: &OutputFormat) -> Writer<Stdout> {
+    let delimiter = match output {
+        OutputFormat::Tsv => b'\t',
+        _ => b',',
+    };
+
+    WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(io::stdout())
+}
+
+/// Write a record, a closed pipe ends the program like it does for the other output formats
+pub fn write_record<I, T>(writer: &mut Writer<Stdout>, record: I) -> Result<()>
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<[u8]>,
+{
+    match writer.write_record(record) {
+        Err(e) if matches!(e.kind(), csv::ErrorKind::Io(e) if e.kind() == io::ErrorKind::BrokenPipe) => {
+            process::exit(0)
+        }
+        result => result.context("Failed to write output"),
+    }
+}
+
+/// Flush the written records, a closed pipe ends the program
+pub fn flush(writer: &mut Writer<Stdout>) -> Result<()> {
+    match writer.flush() {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => process::exit(0),
+        result => result.context("Failed to write output"),
+    }
+}
+
+/// Table of generated passwords with one column per encoding and hash parameter
+pub struct PasswordTable {
+    writer: Writer<Stdout>,
+    encodings: Vec<&'static str>,
+    hash_params: Vec<(&'static str, &'static str)>,
+    username: bool,
+}
+
+impl PasswordTable {
+    /// Start the table by writing the header row. `hash_params` are the encoding and parameter
+    /// names of the hash parameter columns
+    pub fn new(
+        output: &OutputFormat,
+        encodings: &[EncodingFormat],
+        hash_params: Vec<(&'static str, &'static str)>,
+        username: bool,
+    ) -> Result<Self> {
+        let mut table = Self {
+            writer: writer(output),
+            encodings: encodings.iter().map(EncodingFormat::name).collect(),
+            hash_params,
+            username,
+        };
+
+        let mut header = vec!["index".to_string()];
+        if table.username {
+            header.push("username".to_string());
+        }
+        header.push("decoded".to_string());
+        header.extend(table.encodings.iter().map(|name| name.to_string()));
+        header.push("entropy_bits".to_string());
+        header.extend(
+            table
+                .hash_params
+                .iter()
+                .map(|(encoding, parameter)| format!("{}_{}", encoding, parameter)),
+        );
+        write_record(&mut table.writer, header)?;
+
+        Ok(table)
+    }
+
+    pub fn row(&mut self, index: usize, output: &PasswordOutput) -> Result<()> {
+        let mut row = vec![index.to_string()];
+        if self.username {
+            row.push(output.username.clone().unwrap_or_default());
+        }
+        row.push(output.decoded.clone());
+        row.extend(
+            self.encodings
+                .iter()
+                .map(|name| output.encoded.get(*name).cloned().unwrap_or_default()),
+        );
+        row.push(output.entropy_bits.to_string());
+        row.extend(self.hash_params.iter().map(|(encoding, parameter)| {
+            output
+                .hash_params
+                .get(*encoding)
+                .and_then(|parameters| parameters.get(*parameter))
+                .map(u32::to_string)
+                .unwrap_or_default()
+        }));
+
+        write_record(&mut self.writer, row)?;
+        flush(&mut self.writer)
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        flush(&mut self.writer)
+    }
+}
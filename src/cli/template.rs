@@ -0,0 +1,227 @@
+use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
+
+/// Templates that ship with pw, selected by name with `--template`. The header is rendered once
+/// before the first password and only sees the `name` and `seed` placeholders
+const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "env",
+        header: None,
+        item: "{key|upper}={password|shell}",
+        final_newline: true,
+        multiple: true,
+    },
+    Builtin {
+        name: "k8s-secret",
+        header: Some(
+            "apiVersion: v1\nkind: Secret\nmetadata:\n  name: {name}\ntype: Opaque\ndata:",
+        ),
+        item: "  {key}: {base64}",
+        final_newline: true,
+        multiple: true,
+    },
+    // Raw password without a trailing newline, ready for `docker secret create NAME -`
+    Builtin {
+        name: "docker-secret",
+        header: None,
+        item: "{password}",
+        final_newline: false,
+        multiple: false,
+    },
+];
+
+struct Builtin {
+    name: &'static str,
+    header: Option<&'static str>,
+    item: &'static str,
+    final_newline: bool,
+    multiple: bool,
+}
+
+/// How text output is laid out, a built-in template or one from the command line
+#[derive(Debug)]
+pub struct Layout {
+    pub header: Option<Template>,
+    pub item: Template,
+    /// Whether the last item ends with a newline
+    pub final_newline: bool,
+    /// Whether the layout can hold more than one password
+    pub multiple: bool,
+}
+
+impl Layout {
+    /// Resolve `template` to a built-in by name, or parse it as a template for each password
+    pub fn parse(template: &str, known: impl Fn(&str) -> bool) -> Result<Self> {
+        let Some(builtin) = BUILTINS.iter().find(|builtin| builtin.name == template) else {
+            return Ok(Self {
+                header: None,
+                item: Template::parse(template, known)?,
+                final_newline: true,
+                multiple: true,
+            });
+        };
+
+        Ok(Self {
+            header: builtin
+                .header
+                .map(|header| Template::parse(header, |name| name == "name" || name == "seed"))
+                .transpose()?,
+            item: Template::parse(builtin.item, known)?,
+            final_newline: builtin.final_newline,
+            multiple: builtin.multiple,
+        })
+    }
+}
+
+/// Template such as `{password}\t{sha256}`. Placeholders are written in braces and can be piped
+/// through filters, `{key|upper}`. `{{` and `}}` are literal braces and `\t`, `\n` and `\\` are
+/// escapes so templates survive shell quoting
+#[derive(Debug)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug)]
+enum Part {
+    Literal(String),
+    Placeholder(String, Vec<Filter>),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Filter {
+    Upper,
+    Lower,
+    /// POSIX shell single quoting
+    Shell,
+    /// JSON string, which also is a valid YAML and HCL string
+    Json,
+}
+
+impl Filter {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "upper" => Some(Filter::Upper),
+            "lower" => Some(Filter::Lower),
+            "shell" => Some(Filter::Shell),
+            "json" => Some(Filter::Json),
+            _ => None,
+        }
+    }
+
+    fn apply(self, value: &str) -> String {
+        match self {
+            Filter::Upper => value.to_uppercase(),
+            Filter::Lower => value.to_lowercase(),
+            Filter::Shell => format!("'{}'", value.replace('\'', r"'\''")),
+            Filter::Json => serde_json::Value::from(value).to_string(),
+        }
+    }
+}
+
+impl Template {
+    /// Parse a template, failing on unclosed braces, unknown filters and placeholders `known`
+    /// rejects
+    pub fn parse(template: &str, known: impl Fn(&str) -> bool) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('t') => literal.push('\t'),
+                    Some('n') => literal.push('\n'),
+                    Some('\\') => literal.push('\\'),
+                    Some(other) => {
+                        literal.push('\\');
+                        literal.push(other);
+                    }
+                    None => literal.push('\\'),
+                },
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest.find('}').ok_or_else(|| {
+                        anyhow!("Unclosed placeholder in template '{}'", template)
+                    })?;
+                    let mut segments = rest[..end].split('|').map(str::trim);
+                    let name = segments.next().unwrap_or_default();
+
+                    if !known(name) {
+                        return Err(anyhow!(
+                            "Unknown placeholder {{{}}} in template '{}'",
+                            name,
+                            template
+                        ));
+                    }
+
+                    let filters = segments
+                        .map(|filter| {
+                            Filter::from_name(filter).ok_or_else(|| {
+                                anyhow!(
+                                    "Unknown filter '{}' in template '{}'\nAvailable filters: upper, lower, shell, json",
+                                    filter,
+                                    template
+                                )
+                            })
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Placeholder(name.to_string(), filters));
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => {
+                    return Err(anyhow!(
+                        "Unmatched '}}' in template '{}', write '}}}}' for a literal brace",
+                        template
+                    ));
+                }
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Self { parts })
+    }
+
+    /// Names of the placeholders, in order of appearance
+    pub fn placeholders(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Placeholder(name, _) => Some(name.as_str()),
+            Part::Literal(_) => None,
+        })
+    }
+
+    /// Fill in the placeholders, missing values render empty
+    pub fn render(&self, values: &BTreeMap<&str, String>) -> String {
+        let mut rendered = String::new();
+
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => rendered.push_str(text),
+                Part::Placeholder(name, filters) => {
+                    let value = values.get(name.as_str()).cloned().unwrap_or_default();
+                    let value = filters
+                        .iter()
+                        .fold(value, |value, filter| filter.apply(&value));
+                    rendered.push_str(&value);
+                }
+            }
+        }
+
+        rendered
+    }
+}
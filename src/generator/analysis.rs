@@ -0,0 +1,162 @@
+use crate::generator::charset::Charset;
+use crate::generator::entropy::{choice_bits, round_bits};
+use crate::generator::password::random_uppercase_entropy;
+use crate::generator::word::estimate_word_entropy;
+use crate::models::password_analysis::{PasswordAnalysis, Segment, SegmentKind};
+
+// Shorter runs of letters are more likely random characters than a word
+const MIN_WORD_LENGTH: usize = 4;
+
+/// Break an existing password down into the segments a pattern is made of and estimate its entropy.
+///
+/// Letters are words (W), digits are numbers (N), characters of the charset's symbol set are
+/// special characters (C) and anything else is a literal. The entropy is the lower of two
+/// estimates: guessing every character from the character classes the password uses, and
+/// guessing each segment the way `pw` generates it.
+pub fn analyze_password(password: &str, charset: &Charset) -> PasswordAnalysis {
+    let segments = segment_password(password, charset);
+    let count = |class: fn(&char) -> bool| password.chars().filter(class).count();
+
+    let brute_force_bits = brute_force_entropy(password, charset);
+    let segment_bits: f64 = segments.iter().map(|s| segment_entropy(s, charset)).sum();
+
+    PasswordAnalysis {
+        password: password.to_string(),
+        length: password.chars().count(),
+        uppercase: count(char::is_ascii_uppercase),
+        lowercase: count(char::is_ascii_lowercase),
+        digits: count(char::is_ascii_digit),
+        symbols: count(|c| !c.is_alphanumeric() && !c.is_whitespace()),
+        entropy_bits: round_bits(brute_force_bits.min(segment_bits)),
+        pattern: segments.iter().map(pattern_token).collect(),
+        segments,
+    }
+}
+
+fn segment_kind(c: char, charset: &Charset) -> SegmentKind {
+    if c.is_alphabetic() {
+        SegmentKind::Word
+    } else if c.is_ascii_digit() {
+        SegmentKind::Number
+    } else if charset.symbols().contains(&c) {
+        SegmentKind::Symbol
+    } else {
+        SegmentKind::Literal
+    }
+}
+
+fn segment_password(password: &str, charset: &Charset) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = Vec::new();
+
+    for c in password.chars() {
+        let kind = segment_kind(c, charset);
+        match segments.last_mut() {
+            // Every C in a pattern is a single special character
+            Some(last) if last.kind == kind && kind != SegmentKind::Symbol => last.text.push(c),
+            _ => segments.push(Segment {
+                kind,
+                text: c.to_string(),
+            }),
+        }
+    }
+
+    segments
+}
+
+fn pattern_token(segment: &Segment) -> String {
+    match segment.kind {
+        SegmentKind::Word => "W".to_string(),
+        SegmentKind::Number => "N".to_string(),
+        SegmentKind::Symbol => "C".to_string(),
+        SegmentKind::Literal if segment.text.contains('\'') => format!(
+            "\"{}\"",
+            segment.text.replace('\\', "\\\\").replace('"', "\\\"")
+        ),
+        SegmentKind::Literal => format!("'{}'", segment.text.replace('\\', "\\\\")),
+    }
+}
+
+// Every character picked from the union of the character classes that appear in the password,
+// characters outside of the classes each add themselves to the alphabet
+fn brute_force_entropy(password: &str, charset: &Charset) -> f64 {
+    let has = |class: &dyn Fn(char) -> bool| password.chars().any(class);
+
+    let mut alphabet = 0;
+    if has(&|c| c.is_ascii_lowercase()) {
+        alphabet += 26;
+    }
+    if has(&|c| c.is_ascii_uppercase()) {
+        alphabet += 26;
+    }
+    if has(&|c| c.is_ascii_digit()) {
+        alphabet += 10;
+    }
+    if has(&|c| charset.symbols().contains(&c)) {
+        alphabet += charset.symbols().len();
+    }
+
+    let mut others: Vec<char> = password
+        .chars()
+        .filter(|c| !c.is_ascii_alphanumeric() && !charset.symbols().contains(c))
+        .collect();
+    others.sort_unstable();
+    others.dedup();
+    alphabet += others.len();
+
+    password.chars().count() as f64 * choice_bits(alphabet)
+}
+
+fn segment_entropy(segment: &Segment, charset: &Charset) -> f64 {
+    let length = segment.text.chars().count();
+
+    match segment.kind {
+        SegmentKind::Word if length >= MIN_WORD_LENGTH => {
+            let case_bits = if segment.text.chars().any(|c| c.is_uppercase()) {
+                random_uppercase_entropy(&segment.text, charset)
+            } else {
+                0.0
+            };
+            estimate_word_entropy(length, length, length) + case_bits
+        }
+        SegmentKind::Word => {
+            let mixed_case = segment.text.chars().any(|c| c.is_uppercase())
+                && segment.text.chars().any(|c| c.is_lowercase());
+            length as f64 * choice_bits(if mixed_case { 52 } else { 26 })
+        }
+        SegmentKind::Number => length as f64 * choice_bits(10),
+        SegmentKind::Symbol => choice_bits(charset.symbols().len()),
+        SegmentKind::Literal => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::pattern::{Pattern, Token};
+
+    #[test]
+    fn suggested_patterns_parse_back_into_the_literals() {
+        let charset = Charset::new("!", "");
+        for password in ["ab\\cd", "x'\\y", "q\\'\"z!", "a\\\\b"] {
+            let analysis = analyze_password(password, &charset);
+            let pattern = Pattern::parse(&analysis.pattern).unwrap();
+
+            let literals: Vec<&String> = pattern
+                .elements()
+                .iter()
+                .filter_map(|element| match &element.token {
+                    Token::Literal(text) => Some(text),
+                    _ => None,
+                })
+                .collect();
+            let expected: Vec<&String> = analysis
+                .segments
+                .iter()
+                .filter(|segment| segment.kind == SegmentKind::Literal)
+                .map(|segment| &segment.text)
+                .collect();
+            assert_eq!(pattern.elements().len(), analysis.segments.len());
+            assert_eq!(literals, expected, "{}", analysis.pattern);
+        }
+    }
+}
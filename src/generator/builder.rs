@@ -0,0 +1,719 @@
+use crate::generator::charset::Charset;
+use crate::generator::composition::Composition;
+use crate::generator::passphrase::PassphraseOptions;
+use crate::generator::password::{
+    generate_with_min_entropy, random_chars_count, random_uppercase_char, random_uppercase_entropy,
+};
+use crate::generator::pattern::{Pattern, PatternCase};
+use crate::generator::strategy::{
+    DefaultGenerator, Generator, PassphraseGenerator, PatternGenerator, RandomGenerator,
+};
+use crate::generator::wordlist::WordSource;
+use crate::models::generated_password::GeneratedPassword;
+use crate::models::pattern_explanation::PatternExplanation;
+use anyhow::{Result, anyhow};
+use rand::{CryptoRng, RngCore};
+use std::collections::HashSet;
+
+// Longest a password may grow to reach the minimum entropy when no maximum length is set
+const MAX_GROWN_LENGTH: usize = 64;
+// Attempts at generating a password without excluded characters and with the required
+// character classes before giving up
+const REJECTION_ATTEMPTS: usize = 1000;
+// Duplicates in a row before concluding that the options allow no more unique passwords.
+// With 9 of 10 possible passwords taken, this many duplicates happen with a chance of 1e-46
+const UNIQUE_ATTEMPTS: usize = 1000;
+
+enum Mode {
+    Default,
+    Random,
+    Pattern(String),
+    Passphrase(usize),
+}
+
+enum Strategy {
+    Default(DefaultGenerator),
+    Random(RandomGenerator),
+    Pattern(PatternGenerator),
+    Passphrase(PassphraseGenerator),
+}
+
+impl Strategy {
+    fn generate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        min_length: usize,
+        max_length: usize,
+    ) -> Result<GeneratedPassword> {
+        match self {
+            Strategy::Default(generator) => generator.generate(rng, min_length, max_length),
+            Strategy::Random(generator) => generator.generate(rng, min_length, max_length),
+            Strategy::Pattern(generator) => generator.generate(rng, min_length, max_length),
+            Strategy::Passphrase(generator) => generator.generate(rng, min_length, max_length),
+        }
+    }
+}
+
+/// Generates passwords with a fixed set of options, created with [`PasswordGenerator::builder`]
+pub struct PasswordGenerator {
+    strategy: Strategy,
+    charset: Charset,
+    composition: Composition,
+    min_length: usize,
+    max_length: usize,
+    grow_limit: Option<usize>,
+    min_entropy: Option<f64>,
+    random_case: bool,
+    lowercase: bool,
+}
+
+impl PasswordGenerator {
+    pub fn builder() -> PasswordGeneratorBuilder {
+        PasswordGeneratorBuilder::default()
+    }
+
+    /// Generate a password, retrying until it meets the minimum entropy if one is set
+    pub fn generate<R: RngCore + CryptoRng>(&self, rng: &mut R) -> Result<GeneratedPassword> {
+        generate_with_min_entropy(
+            self.min_entropy,
+            self.max_length,
+            self.grow_limit,
+            self.min_entropy_advice(),
+            |max_length| self.generate_once(rng, max_length),
+        )
+    }
+
+    // Words only grow up to their maximum length, patterns and passphrases need more of them
+    fn min_entropy_advice(&self) -> &'static str {
+        match self.strategy {
+            Strategy::Default(_) | Strategy::Random(_) => {
+                "Allow longer passwords with --length or --max-length, or lower --min-entropy"
+            }
+            Strategy::Pattern(_) => "Add more tokens to the pattern, or lower --min-entropy",
+            Strategy::Passphrase(_) => "Use more words with --passphrase, or lower --min-entropy",
+        }
+    }
+
+    /// Explain the pattern of a generator built with [`PasswordGeneratorBuilder::pattern`], with
+    /// the word lengths, words and characters the generator uses
+    pub fn explain_pattern(&self) -> Result<PatternExplanation> {
+        let Strategy::Pattern(generator) = &self.strategy else {
+            return Err(anyhow!("Only patterns can be explained"));
+        };
+
+        generator.pattern.explain(
+            &generator.word_source,
+            &generator.charset,
+            self.min_length,
+            self.max_length,
+            generator.case,
+        )
+    }
+
+    /// Warnings for casing modifiers right after a token of the pattern, see
+    /// [`Pattern::modifier_warnings`]. Empty for the other modes
+    pub fn modifier_warnings(&self) -> Vec<String> {
+        match &self.strategy {
+            Strategy::Pattern(generator) => generator.pattern.modifier_warnings(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Generate `count` distinct passwords. Fails up front when `count` exceeds the number of
+    /// possible passwords where that is known, otherwise when the options cannot produce
+    /// another unique password
+    pub fn generate_unique<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        count: usize,
+    ) -> Result<Vec<GeneratedPassword>> {
+        if let Some(possible) = self.possible_passwords()
+            && count as f64 > possible.round()
+        {
+            return Err(anyhow!(
+                "Cannot generate {} unique passwords, these options allow only {}\n\
+                Lower the number of passwords or allow longer passwords",
+                count,
+                possible.round()
+            ));
+        }
+
+        let mut seen = HashSet::new();
+        let mut passwords = Vec::with_capacity(count);
+        while passwords.len() < count {
+            passwords.push(self.generate_unseen(rng, &mut seen)?);
+        }
+
+        Ok(passwords)
+    }
+
+    // Generate a password that is not in `seen` and add it
+    fn generate_unseen<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        seen: &mut HashSet<String>,
+    ) -> Result<GeneratedPassword> {
+        for _ in 0..UNIQUE_ATTEMPTS {
+            let generated = self.generate(rng)?;
+            if seen.insert(generated.password.clone()) {
+                return Ok(generated);
+            }
+        }
+
+        Err(anyhow!(
+            "Could not generate more than {} unique passwords with these options\n\
+            Lower the number of passwords or allow longer passwords",
+            seen.len()
+        ))
+    }
+
+    // Upper bound on the number of distinct passwords where one is known. The entropy of
+    // generated words is an estimate from below, random case credits less than a bit for each
+    // letter whose case can flip, and growing towards the minimum entropy lifts the bounds
+    fn possible_passwords(&self) -> Option<f64> {
+        if self.min_entropy.is_some() && self.grow_limit.is_some() {
+            return None;
+        }
+
+        match &self.strategy {
+            Strategy::Random(generator) => Some(random_chars_count(
+                &generator.charset,
+                self.min_length,
+                self.max_length,
+            )),
+            Strategy::Pattern(generator)
+                if matches!(generator.word_source, WordSource::List(_))
+                    || !generator.pattern.has_words() =>
+            {
+                if generator.case == PatternCase::Random
+                    && generator
+                        .pattern
+                        .possible_chars(&generator.word_source, &self.charset, PatternCase::Keep)
+                        .iter()
+                        .any(|c| c.is_alphabetic())
+                {
+                    return None;
+                }
+
+                let explanation = self.explain_pattern().ok()?;
+                Some(explanation.bounds.max_entropy_bits.exp2())
+            }
+            _ => None,
+        }
+    }
+
+    // Capitalization can still produce an excluded uppercase letter, and any generator can fall
+    // short of the composition requirements. Reject those passwords instead of steering towards them
+    fn generate_once<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        max_length: usize,
+    ) -> Result<GeneratedPassword> {
+        for _ in 0..REJECTION_ATTEMPTS {
+            let generated = self.generate_candidate(rng, max_length)?;
+            if self.charset.allows_all(&generated.password)
+                && self.composition.is_satisfied_by(&generated.password)
+            {
+                return Ok(generated);
+            }
+        }
+
+        if self.composition.is_empty() {
+            Err(anyhow!(
+                "Could not generate a password without the excluded characters"
+            ))
+        } else {
+            Err(anyhow!(
+                "Could not generate a password with at least {} within {} attempts\nAllow longer passwords or lower the --require-* counts",
+                self.composition.describe(),
+                REJECTION_ATTEMPTS
+            ))
+        }
+    }
+
+    fn generate_candidate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        max_length: usize,
+    ) -> Result<GeneratedPassword> {
+        let mut generated = self.strategy.generate(rng, self.min_length, max_length)?;
+
+        if self.random_case {
+            generated.entropy_bits += random_uppercase_entropy(&generated.password, &self.charset);
+            generated.password = random_uppercase_char(&generated.password, &self.charset, rng);
+        }
+
+        // Patterns lowercase the parts without a casing modifier themselves
+        if self.lowercase && !matches!(self.strategy, Strategy::Pattern(_)) {
+            if matches!(self.strategy, Strategy::Random(_)) {
+                // Lowercasing throws away the random case of every letter
+                let letters = generated.password.chars().filter(|c| c.is_alphabetic());
+                generated.entropy_bits -= letters.count() as f64;
+            }
+            generated.password = generated.password.to_lowercase();
+        }
+
+        Ok(generated)
+    }
+}
+
+/// Options for a [`PasswordGenerator`].
+///
+/// Without a mode the generator shuffles words, a number and special characters together.
+/// Lengths default to 8-15 characters per password, 4-8 letters per passphrase word, and
+/// dictionary words of any length when a wordlist is used.
+pub struct PasswordGeneratorBuilder {
+    mode: Mode,
+    word_source: WordSource,
+    charset: Charset,
+    composition: Composition,
+    length: Option<usize>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    capitalize: bool,
+    lowercase: bool,
+    min_entropy: Option<f64>,
+    separator: String,
+    inject_digit: bool,
+    inject_symbol: bool,
+    strict_pattern: bool,
+}
+
+impl Default for PasswordGeneratorBuilder {
+    fn default() -> Self {
+        Self {
+            mode: Mode::Default,
+            word_source: WordSource::Generated,
+            charset: Charset::default(),
+            composition: Composition::default(),
+            length: None,
+            min_length: None,
+            max_length: None,
+            capitalize: false,
+            lowercase: false,
+            min_entropy: None,
+            separator: "-".to_string(),
+            inject_digit: false,
+            inject_symbol: false,
+            strict_pattern: false,
+        }
+    }
+}
+
+impl PasswordGeneratorBuilder {
+    /// Generate completely random characters
+    pub fn random(mut self) -> Self {
+        self.mode = Mode::Random;
+        self
+    }
+
+    /// Generate passwords following a pattern, see [`Pattern`] for the syntax
+    pub fn pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.mode = Mode::Pattern(pattern.into());
+        self
+    }
+
+    /// Reject letters in the pattern that are no token instead of using them as literal text,
+    /// see [`Pattern::parse_strict`]
+    pub fn strict_pattern(mut self, strict_pattern: bool) -> Self {
+        self.strict_pattern = strict_pattern;
+        self
+    }
+
+    /// Generate passphrases of `word_count` words
+    pub fn passphrase(mut self, word_count: usize) -> Self {
+        self.mode = Mode::Passphrase(word_count);
+        self
+    }
+
+    /// Separator between the words of a passphrase, `-` by default
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Insert a random digit into one of the words of a passphrase
+    pub fn inject_digit(mut self, inject_digit: bool) -> Self {
+        self.inject_digit = inject_digit;
+        self
+    }
+
+    /// Insert a random special character into one of the words of a passphrase
+    pub fn inject_symbol(mut self, inject_symbol: bool) -> Self {
+        self.inject_symbol = inject_symbol;
+        self
+    }
+
+    /// Where the words of patterns and passphrases come from
+    pub fn word_source(mut self, word_source: WordSource) -> Self {
+        self.word_source = word_source;
+        self
+    }
+
+    /// Special characters to use and characters to never produce
+    pub fn charset(mut self, charset: Charset) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    /// Character classes every password must contain, enforced by rejecting passwords that fall short
+    pub fn composition(mut self, composition: Composition) -> Self {
+        self.composition = composition;
+        self
+    }
+
+    /// Fixed length, takes precedence over `min_length` and `max_length`
+    pub fn length(mut self, length: usize) -> Self {
+        self.length = Some(length);
+        self
+    }
+
+    pub fn min_length(mut self, min_length: usize) -> Self {
+        self.min_length = Some(min_length);
+        self
+    }
+
+    pub fn max_length(mut self, max_length: usize) -> Self {
+        self.max_length = Some(max_length);
+        self
+    }
+
+    /// Capitalize every word
+    pub fn capitalize(mut self, capitalize: bool) -> Self {
+        self.capitalize = capitalize;
+        self
+    }
+
+    /// Lowercase the whole password
+    pub fn lowercase(mut self, lowercase: bool) -> Self {
+        self.lowercase = lowercase;
+        self
+    }
+
+    /// Only accept passwords with at least this many bits of estimated entropy
+    pub fn min_entropy(mut self, min_entropy: f64) -> Self {
+        self.min_entropy = Some(min_entropy);
+        self
+    }
+
+    pub fn build(self) -> Result<PasswordGenerator> {
+        let uses_words = matches!(self.mode, Mode::Pattern(_) | Mode::Passphrase(_));
+        let is_passphrase = matches!(self.mode, Mode::Passphrase(_));
+        let uses_wordlist = matches!(self.word_source, WordSource::List(_));
+
+        if uses_wordlist && !uses_words {
+            return Err(anyhow!(
+                "A wordlist can only be used with passphrases or patterns"
+            ));
+        }
+
+        if !is_passphrase && (self.inject_digit || self.inject_symbol) {
+            return Err(anyhow!(
+                "Digits and symbols can only be injected into passphrases"
+            ));
+        }
+
+        if is_passphrase && !self.charset.allows_all(&self.separator) {
+            return Err(anyhow!(
+                "The passphrase separator contains excluded characters"
+            ));
+        }
+
+        if self.lowercase && self.composition.upper > 0 {
+            return Err(anyhow!(
+                "Uppercase letters cannot be required for lowercase passwords"
+            ));
+        }
+
+        if let Some(class) = self.composition.unavailable_class(&self.charset) {
+            return Err(anyhow!("The required {} are all excluded", class));
+        }
+
+        // Passphrases only get digits and symbols from the injections and the separator
+        if is_passphrase {
+            if self.composition.digit > usize::from(self.inject_digit) {
+                return Err(anyhow!(
+                    "A passphrase only contains a digit when one is injected"
+                ));
+            }
+
+            let separator_has_symbol = self
+                .separator
+                .chars()
+                .any(|c| !c.is_alphanumeric() && !c.is_whitespace());
+            if self.composition.symbol > 0 && !self.inject_symbol && !separator_has_symbol {
+                return Err(anyhow!(
+                    "A passphrase only contains symbols when one is injected or used as separator"
+                ));
+            }
+        }
+
+        if self.min_entropy.is_some_and(|bits| bits < 0.0) {
+            return Err(anyhow!("Minimum entropy cannot be negative"));
+        }
+
+        let (default_min_length, default_max_length) = if uses_wordlist {
+            (1, usize::MAX)
+        } else if is_passphrase {
+            (4, 8)
+        } else {
+            (8, 15)
+        };
+
+        let (min_length, max_length) = match self.length {
+            Some(length) => (length, length),
+            None => (
+                self.min_length.unwrap_or(default_min_length),
+                self.max_length.unwrap_or(default_max_length),
+            ),
+        };
+
+        if self.min_length == Some(0) {
+            return Err(anyhow!("Minimum length must be greater than 0"));
+        }
+
+        if self.length == Some(0) {
+            return Err(anyhow!("Length must be greater than 0"));
+        }
+
+        if min_length > max_length {
+            return Err(anyhow!(
+                "Minimum length cannot be greater than maximum length"
+            ));
+        }
+
+        // The length bounds the whole password only without words
+        if matches!(self.mode, Mode::Default | Mode::Random)
+            && self.composition.total() > max_length
+        {
+            return Err(anyhow!(
+                "The required characters ({}) do not fit in {} characters",
+                self.composition.describe(),
+                max_length
+            ));
+        }
+
+        // Without an explicit maximum the passwords may grow to reach the minimum entropy. The
+        // lengths of patterns and passphrases are per word, growing them makes unreadable words
+        let grow_limit = if matches!(self.mode, Mode::Default | Mode::Random)
+            && self.length.is_none()
+            && self.max_length.is_none()
+        {
+            Some(MAX_GROWN_LENGTH)
+        } else {
+            None
+        };
+
+        // Patterns apply random case themselves so characters of classes keep their case
+        let random_case = !self.capitalize && !self.lowercase && matches!(self.mode, Mode::Default);
+        let pattern_case = if self.lowercase {
+            PatternCase::Lower
+        } else if self.capitalize {
+            PatternCase::Capitalize
+        } else {
+            PatternCase::Random
+        };
+
+        let strategy = match self.mode {
+            Mode::Default => Strategy::Default(DefaultGenerator {
+                charset: self.charset.clone(),
+                capitalize: self.capitalize,
+            }),
+            Mode::Random => Strategy::Random(RandomGenerator {
+                charset: self.charset.clone(),
+            }),
+            Mode::Pattern(pattern) => Strategy::Pattern(PatternGenerator {
+                pattern: if self.strict_pattern {
+                    Pattern::parse_strict(&pattern)?
+                } else {
+                    Pattern::parse(&pattern)?
+                },
+                word_source: self.word_source,
+                charset: self.charset.clone(),
+                case: pattern_case,
+            }),
+            Mode::Passphrase(word_count) => {
+                if word_count == 0 {
+                    return Err(anyhow!("A passphrase needs at least one word"));
+                }
+
+                Strategy::Passphrase(PassphraseGenerator {
+                    options: PassphraseOptions {
+                        word_count,
+                        separator: self.separator,
+                        capitalize: self.capitalize,
+                        inject_digit: self.inject_digit,
+                        inject_symbol: self.inject_symbol,
+                    },
+                    word_source: self.word_source,
+                    charset: self.charset.clone(),
+                })
+            }
+        };
+
+        // Requirements a pattern or passphrase can never meet would only burn the rejection attempts
+        match &strategy {
+            Strategy::Pattern(generator) => {
+                let chars = generator.pattern.possible_chars(
+                    &generator.word_source,
+                    &self.charset,
+                    pattern_case,
+                );
+                if let Some(class) = self.composition.missing_class(&chars, &self.charset) {
+                    let advice = match class {
+                        "uppercase letters" => "add U or a casing modifier such as W^",
+                        "lowercase letters" => "add L or W",
+                        "digits" => "add N or D",
+                        _ => "add C",
+                    };
+                    return Err(anyhow!(
+                        "The pattern never contains {}, {} to it",
+                        class,
+                        advice
+                    ));
+                }
+            }
+            Strategy::Passphrase(generator) => {
+                let mut chars = generator.word_source.chars();
+                if self.capitalize {
+                    chars.extend(chars.clone().iter().flat_map(|c| c.to_uppercase()));
+                }
+                chars.extend(generator.options.separator.chars());
+                // Digits and symbols are checked against the injections above
+                let letters = Composition {
+                    upper: self.composition.upper,
+                    lower: self.composition.lower,
+                    ..Composition::default()
+                };
+                match letters.missing_class(&chars, &self.charset) {
+                    Some("uppercase letters") => {
+                        return Err(anyhow!(
+                            "A passphrase only contains uppercase letters with -c (--capitalize)"
+                        ));
+                    }
+                    Some(class) => {
+                        return Err(anyhow!("The passphrase words never contain {}", class));
+                    }
+                    None => {}
+                }
+            }
+            _ => {}
+        }
+
+        Ok(PasswordGenerator {
+            strategy,
+            charset: self.charset,
+            composition: self.composition,
+            min_length,
+            max_length,
+            grow_limit,
+            min_entropy: self.min_entropy,
+            random_case,
+            lowercase: self.lowercase,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::wordlist::Wordlist;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn unique_passwords_fail_up_front_beyond_the_possible_passwords() {
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        let digit = PasswordGenerator::builder().pattern("D").build().unwrap();
+
+        let passwords = digit.generate_unique(&mut rng, 10).unwrap();
+        let distinct: HashSet<_> = passwords.iter().map(|p| &p.password).collect();
+        assert_eq!(distinct.len(), 10);
+
+        let error = digit.generate_unique(&mut rng, 11).unwrap_err();
+        assert!(error.to_string().contains("allow only 10"));
+
+        let random = PasswordGenerator::builder()
+            .random()
+            .length(1)
+            .build()
+            .unwrap();
+        assert!(random.generate_unique(&mut rng, 1000).is_err());
+
+        // Random case gives every letter two outcomes although it credits less than a bit
+        let two_words = PasswordGenerator::builder()
+            .pattern("W")
+            .word_source(WordSource::List(Wordlist::parse("abcd\nefgh").unwrap()))
+            .build()
+            .unwrap();
+        assert!(two_words.generate_unique(&mut rng, 10).is_ok());
+    }
+
+    #[test]
+    fn words_do_not_grow_towards_the_minimum_entropy() {
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        let passphrase = PasswordGenerator::builder()
+            .passphrase(2)
+            .min_entropy(200.0)
+            .build()
+            .unwrap();
+
+        let error = passphrase.generate(&mut rng).unwrap_err();
+        assert!(error.to_string().contains("more words"));
+    }
+
+    #[test]
+    fn rejects_a_zero_length() {
+        for builder in [
+            PasswordGenerator::builder(),
+            PasswordGenerator::builder().random(),
+            PasswordGenerator::builder().passphrase(3),
+        ] {
+            let error = builder.length(0).build().err().unwrap();
+            assert_eq!(error.to_string(), "Length must be greater than 0");
+        }
+    }
+
+    #[test]
+    fn rejects_requirements_the_mode_never_meets() {
+        let error = |builder: PasswordGeneratorBuilder| builder.build().err().unwrap().to_string();
+        let upper = Composition {
+            upper: 1,
+            ..Composition::default()
+        };
+        let digit = Composition {
+            digit: 1,
+            ..Composition::default()
+        };
+
+        let passphrase = PasswordGenerator::builder()
+            .passphrase(3)
+            .composition(upper);
+        assert!(error(passphrase).contains("-c"));
+        let passphrase = PasswordGenerator::builder()
+            .passphrase(3)
+            .composition(upper);
+        assert!(passphrase.capitalize(true).build().is_ok());
+
+        let pattern = PasswordGenerator::builder().pattern("W").composition(digit);
+        assert!(error(pattern).contains("add N or D"));
+        let pattern = PasswordGenerator::builder()
+            .pattern("L{4}")
+            .composition(upper);
+        assert!(error(pattern).contains("add U"));
+        assert!(
+            PasswordGenerator::builder()
+                .pattern("L{4}^")
+                .composition(upper)
+                .build()
+                .is_ok()
+        );
+        assert!(
+            PasswordGenerator::builder()
+                .pattern("W")
+                .composition(upper)
+                .build()
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+/// Special characters used when no other symbol set is configured
+pub const DEFAULT_SYMBOLS: &str = "!@#$%^&*()-_=+[]{}|;:,.<>?~";
+
+const SHELL_SAFE_SYMBOLS: &str = "-_=+.,:@%";
+const URL_SAFE_SYMBOLS: &str = "-._~";
+const AMBIGUOUS_CHARS: &str = "0O1lI|";
+
+/// Named character restrictions for common kinds of target systems
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum CharsetPreset {
+    /// Only symbols that need no quoting in POSIX shells
+    ShellSafe,
+    /// Only symbols that need no percent-encoding in URLs
+    UrlSafe,
+    /// No characters that are easily confused with each other (0 O 1 l I |)
+    NoAmbiguous,
+}
+
+impl CharsetPreset {
+    /// The only symbols the preset allows, `None` when it doesn't restrict symbols
+    pub fn symbols(self) -> Option<&'static str> {
+        match self {
+            CharsetPreset::ShellSafe => Some(SHELL_SAFE_SYMBOLS),
+            CharsetPreset::UrlSafe => Some(URL_SAFE_SYMBOLS),
+            CharsetPreset::NoAmbiguous => None,
+        }
+    }
+
+    fn excluded(self) -> &'static str {
+        match self {
+            CharsetPreset::NoAmbiguous => AMBIGUOUS_CHARS,
+            _ => "",
+        }
+    }
+}
+
+/// The characters generators are allowed to use: a set of special characters
+/// and characters that must never appear in a password.
+#[derive(Debug, Clone)]
+pub struct Charset {
+    symbols: Vec<char>,
+    excluded: Vec<char>,
+}
+
+impl Default for Charset {
+    fn default() -> Self {
+        Self::new(DEFAULT_SYMBOLS, "")
+    }
+}
+
+impl Charset {
+    /// Use `symbols` as special characters and never produce any character in `excluded`
+    pub fn new(symbols: &str, excluded: &str) -> Self {
+        let excluded: Vec<char> = excluded.chars().collect();
+        let mut unique_symbols: Vec<char> = Vec::new();
+        for c in symbols.chars() {
+            if !excluded.contains(&c) && !unique_symbols.contains(&c) {
+                unique_symbols.push(c);
+            }
+        }
+
+        Self {
+            symbols: unique_symbols,
+            excluded,
+        }
+    }
+
+    /// Combine presets with an explicit symbol set and excluded characters.
+    /// Explicit symbols replace the symbols of the presets, exclusions add up.
+    pub fn from_options(
+        presets: &[CharsetPreset],
+        symbols: Option<&str>,
+        exclude: Option<&str>,
+    ) -> Result<Self> {
+        let preset_symbols: Vec<&str> = presets.iter().filter_map(|p| p.symbols()).collect();
+        let symbols: String = match symbols {
+            Some(symbols) => symbols.to_string(),
+            // Symbols allowed by every preset
+            None if !preset_symbols.is_empty() => preset_symbols[0]
+                .chars()
+                .filter(|c| preset_symbols.iter().all(|set| set.contains(*c)))
+                .collect(),
+            None => DEFAULT_SYMBOLS.to_string(),
+        };
+
+        if let Some(c) = symbols
+            .chars()
+            .find(|c| c.is_alphanumeric() || c.is_whitespace())
+        {
+            return Err(anyhow!(
+                "Symbol set can only contain special characters, found '{}'",
+                c
+            ));
+        }
+
+        let mut excluded: String = presets.iter().map(|p| p.excluded()).collect();
+        excluded.push_str(exclude.unwrap_or_default());
+
+        let charset = Self::new(&symbols, &excluded);
+        if charset.alphanumerics().is_empty() && charset.symbols.is_empty() {
+            return Err(anyhow!("Every character has been excluded"));
+        }
+
+        Ok(charset)
+    }
+
+    pub fn symbols(&self) -> &[char] {
+        &self.symbols
+    }
+
+    /// Letters and digits that are not excluded
+    pub fn alphanumerics(&self) -> Vec<char> {
+        ('a'..='z')
+            .chain('A'..='Z')
+            .chain('0'..='9')
+            .filter(|c| self.allows(*c))
+            .collect()
+    }
+
+    pub fn allows(&self, c: char) -> bool {
+        !self.excluded.contains(&c)
+    }
+
+    pub fn allows_all(&self, text: &str) -> bool {
+        text.chars().all(|c| self.allows(c))
+    }
+
+    /// How many numbers with exactly `num_digits` digits (no leading zeros) only use allowed digits
+    pub fn numbers_with_digits(&self, num_digits: u32) -> usize {
+        let digits = ('0'..='9').filter(|c| self.allows(*c)).count();
+        if num_digits <= 1 {
+            return digits;
+        }
+
+        let leading_digits = ('1'..='9').filter(|c| self.allows(*c)).count();
+        leading_digits * digits.pow(num_digits - 1)
+    }
+
+    /// Whether `number` only has digits that are allowed
+    pub fn allows_number(&self, number: usize) -> bool {
+        self.allows_all(&number.to_string())
+    }
+}
@@ -0,0 +1,84 @@
+use crate::generator::charset::Charset;
+use std::collections::HashSet;
+
+/// Minimum number of characters of each class a password must contain.
+///
+/// Generators never aim for these counts, passwords that fall short are rejected and
+/// generated again so the requirements don't bias which passwords are produced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Composition {
+    pub upper: usize,
+    pub lower: usize,
+    pub digit: usize,
+    pub symbol: usize,
+}
+
+impl Composition {
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+
+    /// Fewest characters a password meeting every requirement can have
+    pub fn total(&self) -> usize {
+        self.upper + self.lower + self.digit + self.symbol
+    }
+
+    pub fn is_satisfied_by(&self, password: &str) -> bool {
+        let count = |class: fn(&char) -> bool| password.chars().filter(class).count();
+
+        count(char::is_ascii_uppercase) >= self.upper
+            && count(char::is_ascii_lowercase) >= self.lower
+            && count(char::is_ascii_digit) >= self.digit
+            && count(|c| !c.is_alphanumeric() && !c.is_whitespace()) >= self.symbol
+    }
+
+    /// The first required character class that `charset` excludes completely
+    pub fn unavailable_class(&self, charset: &Charset) -> Option<&'static str> {
+        let available =
+            |mut chars: std::ops::RangeInclusive<char>| chars.any(|c| charset.allows(c));
+
+        if self.upper > 0 && !available('A'..='Z') {
+            Some("uppercase letters")
+        } else if self.lower > 0 && !available('a'..='z') {
+            Some("lowercase letters")
+        } else if self.digit > 0 && !available('0'..='9') {
+            Some("digits")
+        } else {
+            None
+        }
+    }
+
+    /// The first required character class without any allowed character in `chars`, the
+    /// characters a generator can produce
+    pub fn missing_class(&self, chars: &HashSet<char>, charset: &Charset) -> Option<&'static str> {
+        let available =
+            |class: fn(&char) -> bool| chars.iter().any(|c| class(c) && charset.allows(*c));
+
+        if self.upper > 0 && !available(char::is_ascii_uppercase) {
+            Some("uppercase letters")
+        } else if self.lower > 0 && !available(char::is_ascii_lowercase) {
+            Some("lowercase letters")
+        } else if self.digit > 0 && !available(char::is_ascii_digit) {
+            Some("digits")
+        } else if self.symbol > 0 && !available(|c| !c.is_alphanumeric() && !c.is_whitespace()) {
+            Some("symbols")
+        } else {
+            None
+        }
+    }
+
+    /// Human readable list of the requirements, e.g. `1 uppercase, 2 digits`
+    pub fn describe(&self) -> String {
+        [
+            (self.upper, "uppercase", "uppercase"),
+            (self.lower, "lowercase", "lowercase"),
+            (self.digit, "digit", "digits"),
+            (self.symbol, "symbol", "symbols"),
+        ]
+        .iter()
+        .filter(|(count, _, _)| *count > 0)
+        .map(|(count, one, many)| format!("{} {}", count, if *count == 1 { one } else { many }))
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+}
@@ -0,0 +1,85 @@
+use anyhow::{Result, anyhow};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+
+// The cost parameters are part of every derived password. Changing them changes every password
+// derived before, so they are pinned here instead of following the defaults of the crates
+const ARGON2_MEMORY_KIB: u32 = 64 * 1024;
+const ARGON2_ITERATIONS: u32 = 3;
+const ARGON2_PARALLELISM: u32 = 1;
+const SCRYPT_LOG_N: u8 = 17;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+// Keeps derived seeds apart from any other use of the same master passphrase
+const SALT_PREFIX: &str = "pw-derive-v1";
+
+/// Key derivation function turning a master passphrase into a seed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum Kdf {
+    /// Argon2id with 64 MiB of memory and 3 iterations
+    Argon2id,
+    /// scrypt with N = 2^17, r = 8 and p = 1
+    Scrypt,
+}
+
+/// What a password is derived for. The same master passphrase and site always derive the same seed
+#[derive(Debug, Clone)]
+pub struct DeriveInput<'a> {
+    pub site: &'a str,
+    pub user: &'a str,
+    pub counter: u32,
+}
+
+impl DeriveInput<'_> {
+    // Sites are compared case insensitively, `Example.com` and `example.com` derive the same seed.
+    // Every part is length prefixed so no two inputs share a salt
+    fn salt(&self) -> Vec<u8> {
+        let site = self.site.trim().to_lowercase();
+        let mut salt = SALT_PREFIX.as_bytes().to_vec();
+        for part in [site.as_bytes(), self.user.as_bytes()] {
+            salt.extend_from_slice(&(part.len() as u32).to_be_bytes());
+            salt.extend_from_slice(part);
+        }
+        salt.extend_from_slice(&self.counter.to_be_bytes());
+        salt
+    }
+}
+
+/// Derive a 256 bit seed from the master passphrase with the key derivation function
+pub fn derive_seed(master: &str, input: &DeriveInput, kdf: Kdf) -> Result<[u8; 32]> {
+    let salt = input.salt();
+    let mut seed = [0u8; 32];
+
+    match kdf {
+        Kdf::Argon2id => {
+            let params = Params::new(
+                ARGON2_MEMORY_KIB,
+                ARGON2_ITERATIONS,
+                ARGON2_PARALLELISM,
+                Some(seed.len()),
+            )
+            .map_err(|e| anyhow!("Invalid Argon2 parameters: {}", e))?;
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password_into(master.as_bytes(), &salt, &mut seed)
+                .map_err(|e| anyhow!("Argon2 key derivation failed: {}", e))?;
+        }
+        Kdf::Scrypt => {
+            let params = scrypt::Params::new(SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P, seed.len())
+                .map_err(|e| anyhow!("Invalid scrypt parameters: {}", e))?;
+            scrypt::scrypt(master.as_bytes(), &salt, &params, &mut seed)
+                .map_err(|e| anyhow!("scrypt key derivation failed: {}", e))?;
+        }
+    }
+
+    Ok(seed)
+}
+
+/// A ChaCha20 generator seeded from the master passphrase, see [`derive_seed`]
+pub fn derive_rng(master: &str, input: &DeriveInput, kdf: Kdf) -> Result<ChaCha20Rng> {
+    Ok(ChaCha20Rng::from_seed(derive_seed(master, input, kdf)?))
+}
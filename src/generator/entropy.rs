@@ -0,0 +1,30 @@
+// Helpers for estimating the entropy of the random choices made by the generators.
+// All values are Shannon entropy in bits, assuming the choice is made uniformly.
+
+/// Bits gained by picking one of `options` equally likely outcomes
+pub fn choice_bits(options: usize) -> f64 {
+    if options <= 1 {
+        0.0
+    } else {
+        (options as f64).log2()
+    }
+}
+
+/// Bits gained by shuffling `count` distinct components into a random order (log2 of count!)
+pub fn shuffle_bits(count: usize) -> f64 {
+    (2..=count).map(|n| (n as f64).log2()).sum()
+}
+
+/// Bits gained by a single biased coin flip that comes up true with `probability`
+pub fn coin_flip_bits(probability: f64) -> f64 {
+    if probability <= 0.0 || probability >= 1.0 {
+        return 0.0;
+    }
+
+    -(probability * probability.log2() + (1.0 - probability) * (1.0 - probability).log2())
+}
+
+/// Round to two decimals for display and structured output
+pub fn round_bits(bits: f64) -> f64 {
+    (bits * 100.0).round() / 100.0
+}
@@ -1,2 +1,12 @@
+pub mod analysis;
+pub mod builder;
+pub mod charset;
+pub mod composition;
+pub mod derive;
+pub mod entropy;
+pub mod passphrase;
+pub mod password;
+pub mod pattern;
+pub mod strategy;
 pub mod word;
-pub mod password;
\ No newline at end of file
+pub mod wordlist;
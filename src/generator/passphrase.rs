@@ -0,0 +1,116 @@
+use crate::generator::charset::Charset;
+use crate::generator::entropy::choice_bits;
+use crate::generator::password::{get_random_special_chars, special_chars_entropy};
+use crate::generator::word::capitalize;
+use crate::generator::wordlist::WordSource;
+use crate::models::generated_password::GeneratedPassword;
+use anyhow::{Result, anyhow};
+use rand::Rng;
+use rand::{CryptoRng, RngCore};
+
+/// How a passphrase is put together
+#[derive(Debug, Clone)]
+pub struct PassphraseOptions {
+    pub word_count: usize,
+    pub separator: String,
+    pub capitalize: bool,
+    pub inject_digit: bool,
+    pub inject_symbol: bool,
+}
+
+/// Generate a diceware style passphrase: `word_count` words joined by `separator`.
+/// A digit and/or a special character can be injected at a random position in a random word.
+pub fn generate_passphrase<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    options: &PassphraseOptions,
+    word_source: &WordSource,
+    charset: &Charset,
+    min_word_length: usize,
+    max_word_length: usize,
+) -> Result<GeneratedPassword> {
+    let mut entropy_bits = 0.0;
+    let mut words = Vec::with_capacity(options.word_count);
+
+    for _ in 0..options.word_count {
+        let (word, word_entropy) =
+            word_source.random_word(min_word_length, max_word_length, charset, rng)?;
+        entropy_bits += word_entropy;
+
+        let word = if options.capitalize {
+            capitalize(&word)
+        } else {
+            word
+        };
+        words.push(word);
+    }
+
+    if options.inject_digit {
+        let digits: Vec<char> = ('0'..='9').filter(|c| charset.allows(*c)).collect();
+        if digits.is_empty() {
+            return Err(anyhow!("Every digit is excluded, no digit can be injected"));
+        }
+        let digit = digits[rng.random_range(0..digits.len())].to_string();
+        entropy_bits += choice_bits(digits.len()) + inject(rng, &mut words, &digit);
+    }
+
+    if options.inject_symbol {
+        if charset.symbols().is_empty() {
+            return Err(anyhow!(
+                "No special characters are allowed, no symbol can be injected"
+            ));
+        }
+        let symbol = get_random_special_chars(rng, charset, 1);
+        entropy_bits += special_chars_entropy(charset, 1) + inject(rng, &mut words, &symbol);
+    }
+
+    Ok(GeneratedPassword::new(
+        words.join(&options.separator),
+        entropy_bits,
+    ))
+}
+
+// Insert `text` at a random position in a random word, returning the entropy of the placement.
+// Positions are character boundaries, words from a wordlist are not necessarily ASCII
+fn inject<R: RngCore + CryptoRng>(rng: &mut R, words: &mut [String], text: &str) -> f64 {
+    if words.is_empty() {
+        return 0.0;
+    }
+
+    let word_idx = rng.random_range(0..words.len());
+    let word = &mut words[word_idx];
+    let positions: Vec<usize> = word
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain([word.len()])
+        .collect();
+    let position = positions[rng.random_range(0..positions.len())];
+    word.insert_str(position, text);
+
+    choice_bits(words.len()) + choice_bits(positions.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn injects_into_non_ascii_words() {
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        for _ in 0..100 {
+            let mut words = vec!["café".to_string(), "naïve".to_string()];
+            let entropy = inject(&mut rng, &mut words, "7");
+
+            assert!(words.iter().any(|word| word.contains('7')));
+            assert_eq!(
+                words.iter().map(|word| word.chars().count()).sum::<usize>(),
+                10
+            );
+            assert!(
+                entropy == choice_bits(2) + choice_bits(5)
+                    || entropy == choice_bits(2) + choice_bits(6)
+            );
+        }
+    }
+}
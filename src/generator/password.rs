@@ -1,38 +1,82 @@
-use crate::generator::word::{capitalize, generate_random_word};
+use crate::generator::charset::Charset;
+use crate::generator::entropy::{choice_bits, coin_flip_bits, round_bits, shuffle_bits};
+use crate::generator::pattern::{Pattern, PatternCase};
+use crate::generator::word::capitalize;
+use crate::generator::wordlist::WordSource;
+use crate::models::generated_password::GeneratedPassword;
+use crate::models::pattern_explanation::Bounds;
 use anyhow::{Result, anyhow};
 use rand::Rng;
 use rand::distr::{Alphanumeric, SampleString};
 use rand::prelude::SliceRandom;
-use rand_chacha::ChaCha8Rng;
+use rand::{CryptoRng, RngCore};
+
+const RANDOM_SPECIAL_CHAR_PROBABILITY: f64 = 0.1;
+const RANDOM_UPPERCASE_PROBABILITY: f64 = 0.1;
+const MIN_ENTROPY_ATTEMPTS: usize = 200;
+const GROW_ENTROPY_ATTEMPTS: usize = 20;
+const TARGET_LENGTH_ATTEMPTS: usize = 1000;
+
+/// Generate a password of words, numbers and special characters shuffled together,
+/// with a total length between `min_length` and `max_length`
+pub fn generate_password_with_target_length<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    charset: &Charset,
+    min_length: usize,
+    max_length: usize,
+    force_capitalize: bool,
+) -> Result<GeneratedPassword> {
+    for _ in 0..TARGET_LENGTH_ATTEMPTS {
+        let generated =
+            password_with_target_length(rng, charset, min_length, max_length, force_capitalize)?;
+
+        // Verify the password meets our length requirements
+        let length = generated.password.chars().count();
+        if length >= min_length && length <= max_length {
+            return Ok(generated);
+        }
+    }
+
+    Err(anyhow!(
+        "Could not generate a password of {} to {} characters with the allowed characters\n\
+        Allow longer passwords with --length or --max-length, or exclude fewer characters",
+        min_length,
+        max_length
+    ))
+}
 
-pub fn generate_password_with_target_length(
-    rng: &mut ChaCha8Rng,
+// A single attempt of `generate_password_with_target_length`, which can miss the length bounds
+fn password_with_target_length<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    charset: &Charset,
     min_length: usize,
     max_length: usize,
     force_capitalize: bool,
-) -> Result<String> {
+) -> Result<GeneratedPassword> {
     let target_length = rng.random_range(min_length..=max_length);
 
     let mut remaining_length = target_length;
 
     let mut components = Vec::new();
+    let mut entropy_bits = 0.0;
 
     let start_with_word = rng.random_bool(0.7);
 
-    let special_char = get_random_special_chars(rng, 1);
-    components.push(special_char.clone());
-    remaining_length = remaining_length.saturating_sub(special_char.len());
+    // The mandatory special character and number are left out when they don't fit
+    if !charset.symbols().is_empty() && remaining_length >= 1 {
+        let special_char = get_random_special_chars(rng, charset, 1);
+        entropy_bits += special_chars_entropy(charset, 1);
+        remaining_length = remaining_length.saturating_sub(special_char.chars().count());
+        components.push(special_char);
+    }
 
-    let num_digits = rng.random_range(1..=3);
-    let max_val = 10usize.pow(num_digits as u32) - 1;
-    let min_val = if num_digits > 1 {
-        10usize.pow((num_digits - 1) as u32)
-    } else {
-        0
-    };
-    let number = rng.random_range(min_val..=max_val).to_string();
-    components.push(number.clone());
-    remaining_length = remaining_length.saturating_sub(number.len());
+    if let Some((number, number_entropy)) = generate_number(rng, charset)
+        && number.len() <= remaining_length
+    {
+        entropy_bits += number_entropy;
+        remaining_length = remaining_length.saturating_sub(number.len());
+        components.push(number);
+    }
 
     if remaining_length >= 3 {
         let num_words = if remaining_length >= 8 { 2 } else { 1 };
@@ -51,7 +95,13 @@ pub fn generate_password_with_target_length(
                 break;
             }
 
-            let word = generate_random_word(word_min_length, word_max_length, rng);
+            let (word, word_entropy) = WordSource::Generated.random_word(
+                word_min_length,
+                word_max_length,
+                charset,
+                rng,
+            )?;
+            entropy_bits += word_entropy;
 
             let word = if force_capitalize {
                 capitalize(&word)
@@ -60,7 +110,7 @@ pub fn generate_password_with_target_length(
             };
 
             components.push(word.clone());
-            remaining_length = remaining_length.saturating_sub(word.len());
+            remaining_length = remaining_length.saturating_sub(word.chars().count());
 
             if remaining_length < 3 {
                 break;
@@ -70,15 +120,35 @@ pub fn generate_password_with_target_length(
 
     // These will always run if --length is 1 or 2
     if remaining_length >= 2 {
-        if rng.random_bool(0.5) {
+        let remaining_digits = remaining_length.min(3);
+        let max_val = 10usize.pow(remaining_digits as u32) - 1;
+        let allowed_numbers: usize = (1..=remaining_digits as u32)
+            .map(|num_digits| charset.numbers_with_digits(num_digits))
+            .sum();
+        let has_symbols = !charset.symbols().is_empty();
+
+        let add_number = match (allowed_numbers > 0, has_symbols) {
+            (true, true) => {
+                entropy_bits += coin_flip_bits(0.5);
+                rng.random_bool(0.5)
+            }
+            (can_add_number, _) => can_add_number,
+        };
+
+        if add_number {
             // Add another number
-            let remaining_digits = remaining_length.min(3);
-            let max_val = 10usize.pow(remaining_digits as u32) - 1;
-            let number = rng.random_range(0..=max_val).to_string();
+            let number = loop {
+                let number = rng.random_range(0..=max_val);
+                if charset.allows_number(number) {
+                    break number.to_string();
+                }
+            };
+            entropy_bits += choice_bits(allowed_numbers);
             components.push(number);
-        } else {
+        } else if has_symbols {
             // Add more special characters
-            let special = get_random_special_chars(rng, remaining_length);
+            let special = get_random_special_chars(rng, charset, remaining_length);
+            entropy_bits += special_chars_entropy(charset, remaining_length);
             components.push(special);
         }
     }
@@ -102,145 +172,146 @@ pub fn generate_password_with_target_length(
             components.remove(word_idx);
             components.shuffle(rng);
             components.insert(0, word_component);
+            entropy_bits += choice_bits(word_indices.len()) + shuffle_bits(components.len() - 1);
         } else {
             // No word components found, shuffle everything
             components.shuffle(rng);
+            entropy_bits += shuffle_bits(components.len());
         }
     } else {
         // Randomize order
         components.shuffle(rng);
+        entropy_bits += shuffle_bits(components.len());
     }
 
     // Combine components into a password
     let password = components.join("");
 
-    // Verify the password meets our length requirements
-    if password.len() < min_length || password.len() > max_length {
-        return generate_password_with_target_length(rng, min_length, max_length, force_capitalize);
-    }
-
-    Ok(password)
+    Ok(GeneratedPassword::new(password, entropy_bits))
 }
 
-pub fn get_random_special_chars(rng: &mut ChaCha8Rng, count: usize) -> String {
-    let special_chars = "!@#$%^&*()-_=+[]{}|;:,.<>?~";
+/// Pick `count` random special characters from the symbols of `charset`
+pub fn get_random_special_chars<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    charset: &Charset,
+    count: usize,
+) -> String {
+    let symbols = charset.symbols();
     let mut result = String::with_capacity(count);
 
+    if symbols.is_empty() {
+        return result;
+    }
+
     for _ in 0..count {
-        let random_special_char_id = rng.random_range(0..=special_chars.len());
-        if let Some(c) = special_chars.chars().nth(random_special_char_id) {
-            result.push(c);
-        }
+        let random_special_char_id = rng.random_range(0..symbols.len());
+        result.push(symbols[random_special_char_id]);
     }
 
     result
 }
 
-/// Generate a password based on a specific pattern
-/// W = Word, N = Number, C/S = Special Character
-/// "text" or 'text' = Literal text to include
-/// Generate a password based on a specific pattern
-/// W = Word, N = Number, C/S = Special Character
-/// "text" or 'text' = Literal text to include at that position
-/// Generate a password based on a specific pattern
-/// W = Word, N = Number, C/S = Special Character
-/// Any other character = Literal character (no quotes needed)
-/// "text" or 'text' = Optional quoted literal text
-pub fn generate_pattern_password(
-    rng: &mut ChaCha8Rng,
-    pattern: &str,
-    min_word_length: usize,
-    max_word_length: usize,
-    force_capitalize: bool,
-) -> Result<String> {
-    let mut components = Vec::new();
-    let mut chars = pattern.chars().peekable();
-
-    while let Some(c) = chars.next() {
-        match c {
-            '"' => {
-                let mut literal = String::new();
-                let mut found_closing = false;
-
-                while let Some(next_char) = chars.next() {
-                    if next_char == '"' {
-                        found_closing = true;
-                        break;
-                    } else if next_char == '\\' {
-                        if let Some(escaped_char) = chars.next() {
-                            literal.push(escaped_char);
-                        }
-                    } else {
-                        literal.push(next_char);
-                    }
-                }
-
-                if !found_closing {
-                    return Err(anyhow!("Unclosed double quote in pattern"));
-                }
+/// Entropy of `count` characters drawn by `get_random_special_chars`
+pub fn special_chars_entropy(charset: &Charset, count: usize) -> f64 {
+    count as f64 * choice_bits(charset.symbols().len())
+}
 
-                components.push(literal);
-            }
-            '\'' => {
-                let mut literal = String::new();
-                let mut found_closing = false;
-
-                while let Some(next_char) = chars.next() {
-                    if next_char == '\'' {
-                        found_closing = true;
-                        break;
-                    } else if next_char == '\\' {
-                        if let Some(escaped_char) = chars.next() {
-                            literal.push(escaped_char);
-                        }
-                    } else {
-                        literal.push(next_char);
-                    }
-                }
+/// Generate a number with 1 to 3 digits without leading zeros, returning it with its entropy.
+/// Numbers with excluded digits are rejected, `None` when every digit is excluded.
+pub fn generate_number<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    charset: &Charset,
+) -> Option<(String, f64)> {
+    let digit_ranges = number_ranges(charset);
 
-                if !found_closing {
-                    return Err(anyhow!("Unclosed single quote in pattern"));
-                }
+    if digit_ranges.is_empty() {
+        return None;
+    }
 
-                components.push(literal);
-            }
-            'W' | 'w' => {
-                let word = generate_random_word(min_word_length, max_word_length, rng);
-                let word = if force_capitalize {
-                    capitalize(&word)
-                } else {
-                    word
-                };
-                components.push(word);
-            }
-            'N' | 'n' => {
-                let num_digits = rng.random_range(1..=3);
-                let max_val = 10usize.pow(num_digits as u32) - 1;
-                let min_val = if num_digits > 1 {
-                    10usize.pow((num_digits - 1) as u32)
-                } else {
-                    0
-                };
-                let number = rng.random_range(min_val..=max_val).to_string();
-                components.push(number);
-            }
-            'C' | 'c' | 'S' | 's' => {
-                components.push(get_random_special_chars(rng, 1));
-            }
-            _ => {
-                components.push(c.to_string());
-            }
+    let (min_val, max_val, allowed) = digit_ranges[rng.random_range(0..digit_ranges.len())];
+    let number = loop {
+        let number = rng.random_range(min_val..=max_val);
+        if charset.allows_number(number) {
+            break number;
         }
-    }
+    };
+
+    let entropy_bits = choice_bits(digit_ranges.len()) + choice_bits(allowed);
+    Some((number.to_string(), entropy_bits))
+}
+
+/// Lengths and entropy of the numbers `generate_number` produces, `None` when every digit is
+/// excluded
+pub fn number_bounds(charset: &Charset) -> Option<Bounds> {
+    let digit_ranges = number_ranges(charset);
+    let length = |min_val: usize| min_val.max(1).to_string().len();
+
+    digit_ranges
+        .iter()
+        .map(|&(min_val, _, allowed)| Bounds {
+            min_length: length(min_val),
+            max_length: length(min_val),
+            min_entropy_bits: choice_bits(digit_ranges.len()) + choice_bits(allowed),
+            max_entropy_bits: choice_bits(digit_ranges.len()) + choice_bits(allowed),
+        })
+        .reduce(Bounds::or)
+}
+
+// The smallest and largest number of 1, 2 and 3 digits with the count of allowed numbers, for
+// the digit counts with any allowed number
+fn number_ranges(charset: &Charset) -> Vec<(usize, usize, usize)> {
+    (1..=3u32)
+        .map(|num_digits| {
+            let max_val = 10usize.pow(num_digits) - 1;
+            let min_val = if num_digits > 1 {
+                10usize.pow(num_digits - 1)
+            } else {
+                0
+            };
+            (min_val, max_val, charset.numbers_with_digits(num_digits))
+        })
+        .filter(|(_, _, allowed)| *allowed > 0)
+        .collect()
+}
 
-    Ok(components.join(""))
+/// Generate a password following `pattern`, see [`Pattern`] for the syntax.
+/// The word lengths apply to every W in the pattern without its own lengths
+pub fn generate_pattern_password<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    pattern: &str,
+    words: &WordSource,
+    charset: &Charset,
+    min_word_length: usize,
+    max_word_length: usize,
+    force_capitalize: bool,
+) -> Result<GeneratedPassword> {
+    Pattern::parse(pattern)?.generate(
+        rng,
+        words,
+        charset,
+        min_word_length,
+        max_word_length,
+        if force_capitalize {
+            PatternCase::Capitalize
+        } else {
+            PatternCase::Keep
+        },
+    )
 }
 
-pub fn random_uppercase_char(word: &str, rng: &mut ChaCha8Rng) -> String {
+/// Uppercase each letter with a 10% chance, unless the uppercase letter is excluded
+pub fn random_uppercase_char<R: RngCore + CryptoRng>(
+    word: &str,
+    charset: &Charset,
+    rng: &mut R,
+) -> String {
     let mut final_word = String::with_capacity(word.len());
     for c in word.chars() {
-        if c.is_alphabetic() & rng.random_bool(0.1) {
-            final_word.push(c.to_ascii_uppercase())
+        let upper = c.to_ascii_uppercase();
+        if c.is_alphabetic() & rng.random_bool(RANDOM_UPPERCASE_PROBABILITY)
+            && charset.allows(upper)
+        {
+            final_word.push(upper)
         } else {
             final_word.push(c);
         }
@@ -248,19 +319,165 @@ pub fn random_uppercase_char(word: &str, rng: &mut ChaCha8Rng) -> String {
     final_word
 }
 
-pub fn random_chars(rng: &mut ChaCha8Rng, minimum_length: usize, maximum_length: usize) -> String {
+/// Entropy added by `random_uppercase_char`: one biased coin flip per letter that may be uppercased
+pub fn random_uppercase_entropy(word: &str, charset: &Charset) -> f64 {
+    let letters = word
+        .chars()
+        .filter(|c| c.is_alphabetic() && charset.allows(c.to_ascii_uppercase()))
+        .count();
+    letters as f64 * random_uppercase_letter_bits()
+}
+
+/// Entropy `random_uppercase_char` adds to a single letter that may be uppercased
+pub fn random_uppercase_letter_bits() -> f64 {
+    coin_flip_bits(RANDOM_UPPERCASE_PROBABILITY)
+}
+
+/// Generate random letters and digits with a 10% chance of a special character per position
+pub fn random_chars<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    charset: &Charset,
+    minimum_length: usize,
+    maximum_length: usize,
+) -> GeneratedPassword {
+    let symbols = charset.symbols();
+    let alphanumeric_count = charset.alphanumerics().len();
+    let special_char_probability = if symbols.is_empty() {
+        0.0
+    } else if alphanumeric_count == 0 {
+        1.0
+    } else {
+        RANDOM_SPECIAL_CHAR_PROBABILITY
+    };
+
     let mut password = String::new();
-    let special_chars = "!@#$%^&*()-_=+[]{}|;:,.<>?~";
     let password_length = rng.random_range(minimum_length..=maximum_length);
 
     for _ in 0..password_length {
-        let special_char_index = rng.random_range(0..special_chars.len());
-        if rng.random_bool(0.1) {
-            password.push(special_chars.chars().nth(special_char_index).unwrap());
+        if !symbols.is_empty() {
+            let special_char_index = rng.random_range(0..symbols.len());
+            if rng.random_bool(special_char_probability) {
+                password.push(symbols[special_char_index]);
+                continue;
+            }
+        }
+
+        let c = loop {
+            let c = Alphanumeric.sample_string(rng, 1).chars().next().unwrap();
+            if charset.allows(c) {
+                break c;
+            }
+        };
+        password.push(c);
+    }
+
+    let bits_per_char = coin_flip_bits(special_char_probability)
+        + special_char_probability * choice_bits(symbols.len())
+        + (1.0 - special_char_probability) * choice_bits(alphanumeric_count);
+
+    GeneratedPassword::new(password, password_length as f64 * bits_per_char)
+}
+
+/// Number of distinct passwords `random_chars` can produce
+pub fn random_chars_count(charset: &Charset, minimum_length: usize, maximum_length: usize) -> f64 {
+    let alphabet = (charset.symbols().len() + charset.alphanumerics().len()) as f64;
+    (minimum_length..=maximum_length)
+        .map(|length| alphabet.powi(length as i32))
+        .sum()
+}
+
+/// Call `generate` with the maximum length until it produces a password with at least
+/// `min_entropy` bits. When `grow_limit` is set the maximum length is raised step by step up to
+/// that limit, otherwise failing to reach the threshold means the length bounds are too tight.
+/// `advice` tells how to allow more entropy when the threshold is not reached.
+pub fn generate_with_min_entropy<F>(
+    min_entropy: Option<f64>,
+    max_length: usize,
+    grow_limit: Option<usize>,
+    advice: &str,
+    mut generate: F,
+) -> Result<GeneratedPassword>
+where
+    F: FnMut(usize) -> Result<GeneratedPassword>,
+{
+    let Some(min_entropy) = min_entropy else {
+        return generate(max_length);
+    };
+
+    let mut max_length = max_length;
+    let mut best_entropy = 0.0f64;
+    loop {
+        let can_grow = grow_limit.is_some_and(|limit| max_length < limit);
+        let attempts = if can_grow {
+            GROW_ENTROPY_ATTEMPTS
         } else {
-            password.push(Alphanumeric.sample_string(rng, 1).chars().nth(0).unwrap());
+            MIN_ENTROPY_ATTEMPTS
+        };
+
+        for _ in 0..attempts {
+            let generated = generate(max_length)?;
+            if generated.entropy_bits >= min_entropy {
+                return Ok(generated);
+            }
+            best_entropy = best_entropy.max(generated.entropy_bits);
+        }
+
+        if !can_grow {
+            break;
         }
+        max_length += 1;
     }
 
-    password
+    Err(anyhow!(
+        "Could not reach the minimum entropy of {} bits within the length bounds (best attempt: {} bits)\n{}",
+        min_entropy,
+        round_bits(best_entropy),
+        advice
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn generate(
+        rng: &mut ChaCha8Rng,
+        length: usize,
+        charset: &Charset,
+    ) -> Result<GeneratedPassword> {
+        generate_password_with_target_length(rng, charset, length, length, false)
+    }
+
+    #[test]
+    fn fits_one_and_two_characters() {
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        for length in [1, 2] {
+            for _ in 0..100 {
+                let generated = generate(&mut rng, length, &Charset::default()).unwrap();
+                assert_eq!(generated.password.chars().count(), length);
+            }
+        }
+    }
+
+    #[test]
+    fn counts_multi_byte_symbols_as_one_character() {
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        let charset = Charset::new("€", "");
+        for length in [4, 10] {
+            for _ in 0..100 {
+                let generated = generate(&mut rng, length, &charset).unwrap();
+                assert_eq!(generated.password.chars().count(), length);
+            }
+        }
+    }
+
+    #[test]
+    fn fails_when_nothing_fits() {
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        let charset = Charset::new("", "0123456789");
+        let error = generate(&mut rng, 2, &charset).unwrap_err();
+        assert!(error.to_string().contains("2 to 2 characters"));
+    }
 }
@@ -0,0 +1,1505 @@
+use crate::generator::charset::Charset;
+use crate::generator::entropy::choice_bits;
+use crate::generator::password::{
+    generate_number, get_random_special_chars, number_bounds, random_uppercase_char,
+    random_uppercase_entropy, random_uppercase_letter_bits, special_chars_entropy,
+};
+use crate::generator::word::capitalize;
+use crate::generator::wordlist::WordSource;
+use crate::models::generated_password::GeneratedPassword;
+use crate::models::pattern_explanation::{Bounds, PatternExplanation, TokenExplanation};
+use anyhow::{Result, anyhow};
+use rand::{CryptoRng, Rng, RngCore};
+use std::collections::HashSet;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+// Characters with a meaning of their own somewhere in a pattern, quoted when a literal is written
+const SYNTAX_CHARS: &str = "\"'\\[]{}()|?^_~%";
+
+// Largest count, word length or digit count, and the most tokens and characters of fixed length a
+// pattern may produce. Keeps patterns such as `W{999999999}` from running out of memory
+const MAX_QUANTITY: usize = 1024;
+
+/// An inclusive range of counts, written `{n}` or `{min,max}` in a pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quantity {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl Quantity {
+    pub const ONE: Quantity = Quantity { min: 1, max: 1 };
+
+    /// Pick a count, without touching the RNG when there is nothing to choose
+    fn pick<R: RngCore + CryptoRng>(&self, rng: &mut R) -> (usize, f64) {
+        if self.min == self.max {
+            (self.min, 0.0)
+        } else {
+            let count = rng.random_range(self.min..=self.max);
+            (count, choice_bits(self.max - self.min + 1))
+        }
+    }
+}
+
+/// A single pattern token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    /// `W`, with its own word lengths when written as `W[4-6]`
+    Word { lengths: Option<Quantity> },
+    /// `N` is a number of 1 to 3 digits without leading zeros, `N{4}` and `N{2,4}` are digit
+    /// strings of that many digits
+    Number { digits: Option<Quantity> },
+    /// `C` or `S`
+    Symbol,
+    /// A single character of a class, `U`, `L`, `D`, `H`, `V`, `K` or `[abc123]`
+    Class(CharClass),
+    /// Fixed text, a single character or a quoted string
+    Literal(String),
+    /// `(W|N)`, one of the alternatives picked at random. `('-'W)` is a group with a single
+    /// alternative
+    Group(Vec<Vec<Element>>),
+}
+
+/// Classes of single characters. The letters are lowercase apart from [`CharClass::Upper`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CharClass {
+    Upper,
+    Lower,
+    Digit,
+    Hex,
+    Vowel,
+    Consonant,
+    /// A bracketed set such as `[abc123]` or `[a-f0-9]`
+    Custom(Vec<char>),
+}
+
+impl CharClass {
+    fn from_token(c: char) -> Option<Self> {
+        match c {
+            'U' => Some(CharClass::Upper),
+            'L' => Some(CharClass::Lower),
+            'D' => Some(CharClass::Digit),
+            'H' => Some(CharClass::Hex),
+            'V' => Some(CharClass::Vowel),
+            'K' => Some(CharClass::Consonant),
+            _ => None,
+        }
+    }
+
+    /// The characters of the class, before any exclusions
+    pub fn chars(&self) -> Vec<char> {
+        match self {
+            CharClass::Upper => ('A'..='Z').collect(),
+            CharClass::Lower => ('a'..='z').collect(),
+            CharClass::Digit => ('0'..='9').collect(),
+            CharClass::Hex => ('0'..='9').chain('a'..='f').collect(),
+            CharClass::Vowel => "aeiou".chars().collect(),
+            CharClass::Consonant => ('a'..='z').filter(|c| !"aeiou".contains(*c)).collect(),
+            CharClass::Custom(chars) => chars.clone(),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            CharClass::Upper => "uppercase letter".to_string(),
+            CharClass::Lower => "lowercase letter".to_string(),
+            CharClass::Digit => "digit".to_string(),
+            CharClass::Hex => "hex digit".to_string(),
+            CharClass::Vowel => "vowel".to_string(),
+            CharClass::Consonant => "consonant".to_string(),
+            CharClass::Custom(chars) => {
+                format!("character of [{}]", chars.iter().collect::<String>())
+            }
+        }
+    }
+}
+
+/// Casing of a single token, written after it: `W^`, `W_`, `W~` or `W%`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Casing {
+    /// `^`, UPPERCASE
+    Upper,
+    /// `_`, lowercase
+    Lower,
+    /// `~`, Capitalized
+    Capitalized,
+    /// `%`, every letter upper or lowercase with equal chance
+    Random,
+}
+
+impl Casing {
+    fn from_modifier(c: char) -> Option<Self> {
+        match c {
+            '^' => Some(Casing::Upper),
+            '_' => Some(Casing::Lower),
+            '~' => Some(Casing::Capitalized),
+            '%' => Some(Casing::Random),
+            _ => None,
+        }
+    }
+}
+
+/// Casing of the parts of a pattern without a [`Casing`] modifier, from `--capitalize`,
+/// `--lowercase` or neither
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternCase {
+    /// Words stay lowercase
+    Keep,
+    /// Capitalize every word
+    Capitalize,
+    /// Lowercase everything
+    Lower,
+    /// Uppercase letters of words and literals at random, see [`random_uppercase_char`]
+    Random,
+}
+
+/// A token and how many times it repeats, `W{2}` is two words. Optional elements, `W?`, are
+/// left out half of the time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Element {
+    pub token: Token,
+    pub repeat: Quantity,
+    pub casing: Option<Casing>,
+    pub optional: bool,
+}
+
+/// A parsed pattern such as `W-W-N{4}`.
+///
+/// - `W`: word, `W[4-6]` or `W[5]` overrides the word lengths
+/// - `N`: number of 1 to 3 digits, `N{4}` exactly 4 digits, `N{2,4}` 2 to 4 digits
+/// - `C` or `S`: special character
+/// - `U`, `L`, `D`, `H`, `V`, `K`: an uppercase letter, lowercase letter, digit, lowercase hex
+///   digit, vowel or consonant
+/// - `[abc123]` or `[a-f0-9]`: a character of the set, backslash escapes `]`, `-` and `\\`
+/// - `{n}` or `{min,max}` after any token but `N` and unquoted literals: repeat the token, up
+///   to 1024 times and 1024 tokens and characters in total
+/// - `(W|N)`: one of the alternatives, `|` also separates alternatives outside of parentheses
+/// - `^`, `_`, `~` or `%` after a token or group: UPPER, lower, Capitalized or rAndOm case for
+///   that token only, e.g. `W^-W_`. A modifier wins over `--capitalize` and `--lowercase`
+/// - `?` after a token or group: leave it out half of the time, e.g. `W('-'N)?`
+/// - `"text"` or `'text'`: literal text, backslash escapes the next character
+/// - Any other character: literal, [`Pattern::parse_strict`] rejects letters
+///
+/// `W`, `N`, `C` and `S` can also be written in lowercase, the character classes are uppercase
+/// only.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    elements: Vec<Element>,
+    // Runs of letters outside of quotes that are literal text, likely typos
+    unquoted_letters: Vec<String>,
+}
+
+impl Pattern {
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let mut chars = pattern.chars().peekable();
+        let mut unquoted_letters = Vec::new();
+        let mut alternatives = parse_alternatives(&mut chars, &mut unquoted_letters)?;
+
+        if chars.next().is_some() {
+            return Err(anyhow!(
+                "Unmatched ')' in pattern, quote it for a literal parenthesis"
+            ));
+        }
+
+        let elements = if alternatives.len() == 1 {
+            alternatives.remove(0)
+        } else {
+            vec![Element {
+                token: Token::Group(alternatives),
+                repeat: Quantity::ONE,
+                casing: None,
+                optional: false,
+            }]
+        };
+
+        if size(&elements) > MAX_QUANTITY {
+            return Err(anyhow!(
+                "Pattern '{}' is too large, it may produce at most {} tokens and characters",
+                pattern,
+                MAX_QUANTITY
+            ));
+        }
+
+        Ok(Self {
+            elements,
+            unquoted_letters,
+        })
+    }
+
+    /// Like [`Pattern::parse`], but letters that are no token have to be quoted to be literal text
+    pub fn parse_strict(pattern: &str) -> Result<Self> {
+        let parsed = Self::parse(pattern)?;
+
+        match parsed.unquoted_letters.first() {
+            Some(letters) => Err(anyhow!(
+                "Unknown pattern token '{}' in '{}', quote literal text: \"'{}'\"",
+                letters,
+                pattern,
+                letters
+            )),
+            None => Ok(parsed),
+        }
+    }
+
+    pub fn elements(&self) -> &[Element] {
+        &self.elements
+    }
+
+    /// Letters outside of quotes that are used as literal text, in runs such as `oo` of `cool`
+    pub fn unquoted_letters(&self) -> &[String] {
+        &self.unquoted_letters
+    }
+
+    /// Warnings for casing modifiers right after a token. Before modifiers existed `W_N` was a
+    /// word, an underscore and a number, it now is a lowercase word and a number
+    pub fn modifier_warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        modifier_warnings(&self.elements, &mut warnings);
+        warnings
+    }
+
+    /// Every character the pattern can produce with `case` applied, before exclusions. Letters
+    /// whose case may change are included in both cases
+    pub fn possible_chars(
+        &self,
+        words: &WordSource,
+        charset: &Charset,
+        case: PatternCase,
+    ) -> HashSet<char> {
+        sequence_chars(&self.elements, words, charset, case)
+    }
+
+    /// Whether the pattern has a `W` token, also within groups
+    pub fn has_words(&self) -> bool {
+        contains_words(&self.elements)
+    }
+
+    /// Generate a password following the pattern. `min_word_length` and `max_word_length`
+    /// apply to every `W` without its own lengths and `case` to every part without a [`Casing`]
+    pub fn generate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        words: &WordSource,
+        charset: &Charset,
+        min_word_length: usize,
+        max_word_length: usize,
+        case: PatternCase,
+    ) -> Result<GeneratedPassword> {
+        let mut generation = Generation {
+            words,
+            charset,
+            min_word_length,
+            max_word_length,
+            capitalize: case == PatternCase::Capitalize,
+            parts: Vec::new(),
+            entropy_bits: 0.0,
+        };
+        generation.sequence(rng, &self.elements)?;
+
+        let mut password = String::new();
+        let mut entropy_bits = generation.entropy_bits;
+        for part in generation.parts {
+            match case {
+                PatternCase::Random if !part.fixed_case && !part.modified => {
+                    entropy_bits += random_uppercase_entropy(&part.text, charset);
+                    password.push_str(&random_uppercase_char(&part.text, charset, rng));
+                }
+                PatternCase::Lower if !part.modified => {
+                    password.push_str(&part.text.to_lowercase());
+                }
+                _ => password.push_str(&part.text),
+            }
+        }
+
+        Ok(GeneratedPassword::new(password, entropy_bits))
+    }
+}
+
+// Generated text of a single token. Characters of classes keep their case under random case and
+// parts with a casing modifier keep theirs under anything
+struct Part {
+    text: String,
+    fixed_case: bool,
+    modified: bool,
+}
+
+impl Pattern {
+    /// Describe what every element of the pattern produces, with the length and entropy of the
+    /// passwords, using the same options as [`Pattern::generate`]
+    pub fn explain(
+        &self,
+        words: &WordSource,
+        charset: &Charset,
+        min_word_length: usize,
+        max_word_length: usize,
+        case: PatternCase,
+    ) -> Result<PatternExplanation> {
+        let mut explainer = Explainer {
+            words,
+            charset,
+            min_word_length,
+            max_word_length,
+            case,
+            warnings: Vec::new(),
+        };
+
+        for letters in &self.unquoted_letters {
+            explainer.warnings.push(format!(
+                "'{}' is not a pattern token but literal text, quote it as \"'{}'\" if that is intended",
+                letters, letters
+            ));
+        }
+        explainer.warnings.extend(self.modifier_warnings());
+
+        let tokens = self
+            .elements
+            .iter()
+            .map(|element| explainer.element(element, None))
+            .collect::<Result<Vec<_>>>()?;
+        let bounds = tokens
+            .iter()
+            .fold(Bounds::fixed(0), |bounds, token| bounds.then(token.bounds));
+
+        if bounds.min_length == 0 {
+            explainer
+                .warnings
+                .push("The pattern can produce an empty password".to_string());
+        }
+
+        Ok(PatternExplanation {
+            pattern: self.to_string(),
+            bounds,
+            tokens,
+            warnings: explainer.warnings,
+        })
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.elements
+            .iter()
+            .try_for_each(|element| write!(f, "{}", element))
+    }
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Quantities and modifiers only apply to quoted literals
+        match &self.token {
+            Token::Literal(text) if self.repeat != Quantity::ONE || self.casing.is_some() => {
+                write!(f, "{}", quote(text))?
+            }
+            token => write!(f, "{}", token)?,
+        }
+        if self.repeat != Quantity::ONE {
+            write!(f, "{}", self.repeat)?;
+        }
+        if let Some(casing) = self.casing {
+            write!(f, "{}", casing)?;
+        }
+        if self.optional {
+            write!(f, "?")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word { lengths: None } => write!(f, "W"),
+            Token::Word {
+                lengths: Some(lengths),
+            } if lengths.min == lengths.max => write!(f, "W[{}]", lengths.min),
+            Token::Word {
+                lengths: Some(lengths),
+            } => write!(f, "W[{}-{}]", lengths.min, lengths.max),
+            Token::Number { digits: None } => write!(f, "N"),
+            Token::Number {
+                digits: Some(digits),
+            } => write!(f, "N{}", digits),
+            Token::Symbol => write!(f, "C"),
+            Token::Class(class) => write!(f, "{}", class),
+            Token::Literal(text) => {
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None)
+                        if !SYNTAX_CHARS.contains(c)
+                            && CharClass::from_token(c).is_none()
+                            && !"WwNnCcSs".contains(c) =>
+                    {
+                        write!(f, "{}", c)
+                    }
+                    _ => write!(f, "{}", quote(text)),
+                }
+            }
+            Token::Group(alternatives) => {
+                write!(f, "(")?;
+                for (index, alternative) in alternatives.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, "|")?;
+                    }
+                    alternative
+                        .iter()
+                        .try_for_each(|element| write!(f, "{}", element))?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+impl fmt::Display for CharClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CharClass::Upper => write!(f, "U"),
+            CharClass::Lower => write!(f, "L"),
+            CharClass::Digit => write!(f, "D"),
+            CharClass::Hex => write!(f, "H"),
+            CharClass::Vowel => write!(f, "V"),
+            CharClass::Consonant => write!(f, "K"),
+            CharClass::Custom(chars) => {
+                let escape = |c: char| {
+                    if "]-\\".contains(c) {
+                        format!("\\{}", c)
+                    } else {
+                        c.to_string()
+                    }
+                };
+                write!(f, "[")?;
+                for (first, last) in char_ranges(chars) {
+                    if first == last {
+                        write!(f, "{}", escape(first))?;
+                    } else {
+                        write!(f, "{}-{}", escape(first), escape(last))?;
+                    }
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Casing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifier = match self {
+            Casing::Upper => '^',
+            Casing::Lower => '_',
+            Casing::Capitalized => '~',
+            Casing::Random => '%',
+        };
+        write!(f, "{}", modifier)
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.min == self.max {
+            write!(f, "{{{}}}", self.min)
+        } else {
+            write!(f, "{{{},{}}}", self.min, self.max)
+        }
+    }
+}
+
+// Runs of three or more consecutive characters, such as `a` to `z`, as a single range
+fn char_ranges(chars: &[char]) -> Vec<(char, char)> {
+    let mut ranges: Vec<(char, char)> = Vec::new();
+
+    for &c in chars {
+        match ranges.last_mut() {
+            Some((_, last)) if char::from_u32(*last as u32 + 1) == Some(c) => *last = c,
+            _ => ranges.push((c, c)),
+        }
+    }
+
+    // A run of two reads better as two characters than as a range
+    ranges
+        .into_iter()
+        .flat_map(|(first, last)| {
+            if last as u32 == first as u32 + 1 {
+                vec![(first, first), (last, last)]
+            } else {
+                vec![(first, last)]
+            }
+        })
+        .collect()
+}
+
+fn describe_chars(chars: &[char]) -> String {
+    char_ranges(chars)
+        .into_iter()
+        .map(|(first, last)| {
+            if first == last {
+                first.to_string()
+            } else {
+                format!("{}-{}", first, last)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn plural(count: usize, one: &str, many: &str) -> String {
+    format!("{} {}", count, if count == 1 { one } else { many })
+}
+
+fn count_range(quantity: Quantity, one: &str, many: &str) -> String {
+    if quantity.min == quantity.max {
+        plural(quantity.min, one, many)
+    } else {
+        format!("{} to {} {}", quantity.min, quantity.max, many)
+    }
+}
+
+// Explanation of a pattern, collecting the warnings along the way
+struct Explainer<'a> {
+    words: &'a WordSource,
+    charset: &'a Charset,
+    min_word_length: usize,
+    max_word_length: usize,
+    case: PatternCase,
+    warnings: Vec<String>,
+}
+
+impl Explainer<'_> {
+    // `inherited` is the casing modifier of the enclosing group, if any
+    fn element(
+        &mut self,
+        element: &Element,
+        inherited: Option<Casing>,
+    ) -> Result<TokenExplanation> {
+        let casing = element.casing.or(inherited);
+        let (mut output, single, children) = self.token(&element.token, casing)?;
+
+        let repeat = element.repeat;
+        let repeat_bits = choice_bits(repeat.max - repeat.min + 1);
+        let mut bounds = Bounds {
+            min_length: single.min_length * repeat.min,
+            max_length: single.max_length.saturating_mul(repeat.max),
+            min_entropy_bits: single.min_entropy_bits * repeat.min as f64 + repeat_bits,
+            max_entropy_bits: single.max_entropy_bits * repeat.max as f64 + repeat_bits,
+        };
+
+        if repeat != Quantity::ONE {
+            output.push_str(&format!(", {}", count_range(repeat, "time", "times")));
+        }
+        if let Some(casing) = element.casing {
+            let name = match casing {
+                Casing::Upper => "UPPERCASE",
+                Casing::Lower => "lowercase",
+                Casing::Capitalized => "Capitalized",
+                Casing::Random => "rAnDoM case",
+            };
+            output.push_str(&format!(", {}", name));
+        }
+        if element.optional {
+            output.push_str(", optional");
+            bounds = Bounds {
+                min_length: 0,
+                max_length: bounds.max_length,
+                min_entropy_bits: choice_bits(2),
+                max_entropy_bits: bounds.max_entropy_bits + choice_bits(2),
+            };
+        }
+
+        Ok(TokenExplanation {
+            token: element.to_string(),
+            output,
+            bounds,
+            children,
+        })
+    }
+
+    // What a single repetition of the token produces
+    fn token(
+        &mut self,
+        token: &Token,
+        casing: Option<Casing>,
+    ) -> Result<(String, Bounds, Vec<TokenExplanation>)> {
+        let (output, bounds, letters) = match token {
+            Token::Word { lengths } => {
+                let (min_length, max_length) = lengths
+                    .map_or((self.min_word_length, self.max_word_length), |lengths| {
+                        (lengths.min, lengths.max)
+                    });
+                let bounds = self
+                    .words
+                    .word_bounds(min_length, max_length, self.charset)?;
+                let length = count_range(
+                    Quantity {
+                        min: bounds.min_length,
+                        max: bounds.max_length,
+                    },
+                    "letter",
+                    "letters",
+                );
+                let output = match self.words {
+                    WordSource::Generated => format!("pronounceable word of {}", length),
+                    WordSource::List(_) => format!(
+                        "one of {:.0} wordlist words of {}",
+                        bounds.min_entropy_bits.exp2(),
+                        length
+                    ),
+                };
+                let letters = (bounds.min_length, bounds.max_length, true);
+                (output, bounds, letters)
+            }
+            Token::Number { digits: None } => {
+                let bounds = number_bounds(self.charset)
+                    .ok_or_else(|| anyhow!("Every digit is excluded, N cannot be used"))?;
+                let output = format!(
+                    "number of {} without leading zeros",
+                    count_range(
+                        Quantity {
+                            min: bounds.min_length,
+                            max: bounds.max_length,
+                        },
+                        "digit",
+                        "digits",
+                    )
+                );
+                (output, bounds, (0, 0, false))
+            }
+            Token::Number {
+                digits: Some(digits),
+            } => {
+                let allowed = allowed_chars(&CharClass::Digit, self.charset)?;
+                let length_bits = choice_bits(digits.max - digits.min + 1);
+                let bounds = Bounds {
+                    min_length: digits.min,
+                    max_length: digits.max,
+                    min_entropy_bits: length_bits + digits.min as f64 * choice_bits(allowed.len()),
+                    max_entropy_bits: length_bits + digits.max as f64 * choice_bits(allowed.len()),
+                };
+                let output = format!(
+                    "{} of {}",
+                    count_range(*digits, "digit", "digits"),
+                    describe_chars(&allowed)
+                );
+                (output, bounds, (0, 0, false))
+            }
+            Token::Symbol => {
+                if self.charset.symbols().is_empty() {
+                    return Err(anyhow!(
+                        "No special characters are allowed, C cannot be used"
+                    ));
+                }
+                let bits = special_chars_entropy(self.charset, 1);
+                let bounds = Bounds {
+                    min_entropy_bits: bits,
+                    max_entropy_bits: bits,
+                    ..Bounds::fixed(1)
+                };
+                let symbols: String = self.charset.symbols().iter().collect();
+                (
+                    format!("special character, one of {}", symbols),
+                    bounds,
+                    (0, 0, false),
+                )
+            }
+            Token::Class(class) => {
+                let allowed = allowed_chars(class, self.charset)?;
+                let bits = choice_bits(allowed.len());
+                let bounds = Bounds {
+                    min_entropy_bits: bits,
+                    max_entropy_bits: bits,
+                    ..Bounds::fixed(1)
+                };
+                let name = match class {
+                    CharClass::Custom(_) => "character".to_string(),
+                    class => class.describe(),
+                };
+                let letters = (
+                    usize::from(allowed.iter().all(|c| c.is_alphabetic())),
+                    usize::from(allowed.iter().any(|c| c.is_alphabetic())),
+                    false,
+                );
+                (
+                    format!("{}, one of {}", name, describe_chars(&allowed)),
+                    bounds,
+                    letters,
+                )
+            }
+            Token::Literal(text) => {
+                if let Err(e) = check_literal(self.charset, text) {
+                    self.warnings.push(e.to_string());
+                }
+                let letters = text
+                    .chars()
+                    .filter(|c| c.is_alphabetic() && self.charset.allows(c.to_ascii_uppercase()))
+                    .count();
+                (
+                    format!("literal {:?}", text),
+                    Bounds::fixed(text.chars().count()),
+                    (letters, letters, true),
+                )
+            }
+            Token::Group(alternatives) => {
+                let mut children = alternatives
+                    .iter()
+                    .map(|alternative| self.sequence(alternative, casing))
+                    .collect::<Result<Vec<_>>>()?;
+                let mut bounds = children
+                    .iter()
+                    .map(|child| child.bounds)
+                    .reduce(Bounds::or)
+                    .unwrap_or(Bounds::fixed(0));
+
+                if alternatives.len() == 1 {
+                    // The elements of a single alternative are the children of the group
+                    let only = children.remove(0);
+                    let children = if alternatives[0].len() == 1 {
+                        vec![only]
+                    } else {
+                        only.children
+                    };
+                    return Ok(("group".to_string(), bounds, children));
+                }
+
+                let bits = choice_bits(alternatives.len());
+                bounds.min_entropy_bits += bits;
+                bounds.max_entropy_bits += bits;
+                let output = format!("one of {} alternatives", alternatives.len());
+                return Ok((output, bounds, children));
+            }
+        };
+
+        // Random case adds a bit per letter for %, and a biased coin flip per letter of words and
+        // literals without a modifier when the password gets random case
+        let (min_letters, max_letters, free) = letters;
+        let letter_bits = match casing {
+            Some(Casing::Random) => choice_bits(2),
+            None if free && self.case == PatternCase::Random => random_uppercase_letter_bits(),
+            _ => 0.0,
+        };
+        let bounds = Bounds {
+            min_entropy_bits: bounds.min_entropy_bits + min_letters as f64 * letter_bits,
+            max_entropy_bits: bounds.max_entropy_bits + max_letters as f64 * letter_bits,
+            ..bounds
+        };
+
+        Ok((output, bounds, Vec::new()))
+    }
+
+    // An alternative of a group, a single element or the elements in a row
+    fn sequence(
+        &mut self,
+        elements: &[Element],
+        casing: Option<Casing>,
+    ) -> Result<TokenExplanation> {
+        let children = elements
+            .iter()
+            .map(|element| self.element(element, casing))
+            .collect::<Result<Vec<_>>>()?;
+
+        if let [only] = children.as_slice() {
+            return Ok(only.clone());
+        }
+
+        let bounds = children
+            .iter()
+            .fold(Bounds::fixed(0), |bounds, child| bounds.then(child.bounds));
+        Ok(TokenExplanation {
+            token: elements.iter().map(ToString::to_string).collect(),
+            output: format!("{} in a row", plural(children.len(), "element", "elements")),
+            bounds,
+            children,
+        })
+    }
+}
+
+// State of a single password while walking the pattern
+struct Generation<'a> {
+    words: &'a WordSource,
+    charset: &'a Charset,
+    min_word_length: usize,
+    max_word_length: usize,
+    capitalize: bool,
+    parts: Vec<Part>,
+    entropy_bits: f64,
+}
+
+impl Generation<'_> {
+    fn sequence<R: RngCore + CryptoRng>(
+        &mut self,
+        rng: &mut R,
+        elements: &[Element],
+    ) -> Result<()> {
+        for element in elements {
+            if element.optional {
+                self.entropy_bits += choice_bits(2);
+                if !rng.random_bool(0.5) {
+                    continue;
+                }
+            }
+
+            let (count, count_entropy) = element.repeat.pick(rng);
+            self.entropy_bits += count_entropy;
+
+            let first_part = self.parts.len();
+            for _ in 0..count {
+                self.token(rng, &element.token)?;
+            }
+
+            // Modifiers nested in a group are more specific and are kept
+            if let Some(casing) = element.casing {
+                for part in &mut self.parts[first_part..] {
+                    if !part.modified {
+                        part.text = apply_casing(
+                            &part.text,
+                            casing,
+                            self.charset,
+                            rng,
+                            &mut self.entropy_bits,
+                        );
+                        part.modified = true;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn token<R: RngCore + CryptoRng>(&mut self, rng: &mut R, token: &Token) -> Result<()> {
+        let mut fixed_case = false;
+        let text = match token {
+            Token::Word { lengths } => {
+                let (min_length, max_length) = lengths
+                    .map_or((self.min_word_length, self.max_word_length), |lengths| {
+                        (lengths.min, lengths.max)
+                    });
+                let (word, word_entropy) =
+                    self.words
+                        .random_word(min_length, max_length, self.charset, rng)?;
+                self.entropy_bits += word_entropy;
+                if self.capitalize {
+                    capitalize(&word)
+                } else {
+                    word
+                }
+            }
+            Token::Number { digits: None } => {
+                let (number, number_entropy) = generate_number(rng, self.charset)
+                    .ok_or_else(|| anyhow!("Every digit is excluded, N cannot be used"))?;
+                self.entropy_bits += number_entropy;
+                number
+            }
+            Token::Number {
+                digits: Some(digits),
+            } => {
+                let allowed = allowed_chars(&CharClass::Digit, self.charset)?;
+                let (length, length_entropy) = digits.pick(rng);
+                self.entropy_bits += length_entropy + length as f64 * choice_bits(allowed.len());
+                (0..length)
+                    .map(|_| allowed[rng.random_range(0..allowed.len())])
+                    .collect()
+            }
+            Token::Symbol => {
+                if self.charset.symbols().is_empty() {
+                    return Err(anyhow!(
+                        "No special characters are allowed, C cannot be used"
+                    ));
+                }
+                self.entropy_bits += special_chars_entropy(self.charset, 1);
+                get_random_special_chars(rng, self.charset, 1)
+            }
+            Token::Class(class) => {
+                let allowed = allowed_chars(class, self.charset)?;
+                self.entropy_bits += choice_bits(allowed.len());
+                fixed_case = true;
+                allowed[rng.random_range(0..allowed.len())].to_string()
+            }
+            Token::Literal(literal) => {
+                check_literal(self.charset, literal)?;
+                literal.clone()
+            }
+            Token::Group(alternatives) => {
+                let alternative = if alternatives.len() == 1 {
+                    &alternatives[0]
+                } else {
+                    self.entropy_bits += choice_bits(alternatives.len());
+                    &alternatives[rng.random_range(0..alternatives.len())]
+                };
+                return self.sequence(rng, alternative);
+            }
+        };
+        self.parts.push(Part {
+            text,
+            fixed_case,
+            modified: false,
+        });
+
+        Ok(())
+    }
+}
+
+fn apply_casing<R: RngCore + CryptoRng>(
+    text: &str,
+    casing: Casing,
+    charset: &Charset,
+    rng: &mut R,
+    entropy_bits: &mut f64,
+) -> String {
+    match casing {
+        Casing::Upper => change_case(text, charset, char::to_uppercase),
+        Casing::Lower => change_case(text, charset, char::to_lowercase),
+        Casing::Capitalized => {
+            let lower = change_case(text, charset, char::to_lowercase);
+            let mut chars = lower.chars();
+            match chars.next() {
+                Some(first) => {
+                    change_case(&first.to_string(), charset, char::to_uppercase) + chars.as_str()
+                }
+                None => lower,
+            }
+        }
+        Casing::Random => text
+            .chars()
+            .map(|c| {
+                let lower = single_case(c, charset, char::to_lowercase);
+                let upper = single_case(c, charset, char::to_uppercase);
+                if lower == upper {
+                    lower
+                } else {
+                    *entropy_bits += choice_bits(2);
+                    if rng.random_bool(0.5) { upper } else { lower }
+                }
+            })
+            .collect(),
+    }
+}
+
+// Change the case of every letter, keeping those whose other case is excluded
+fn change_case<I: Iterator<Item = char>>(
+    text: &str,
+    charset: &Charset,
+    convert: impl Fn(char) -> I,
+) -> String {
+    text.chars()
+        .map(|c| single_case(c, charset, &convert))
+        .collect()
+}
+
+fn single_case<I: Iterator<Item = char>>(
+    c: char,
+    charset: &Charset,
+    convert: impl Fn(char) -> I,
+) -> char {
+    let mut converted = convert(c);
+    match (converted.next(), converted.next()) {
+        (Some(other), None) if charset.allows(other) => other,
+        _ => c,
+    }
+}
+
+fn allowed_chars(class: &CharClass, charset: &Charset) -> Result<Vec<char>> {
+    let allowed: Vec<char> = class
+        .chars()
+        .into_iter()
+        .filter(|c| charset.allows(*c))
+        .collect();
+
+    if allowed.is_empty() {
+        return Err(anyhow!(
+            "Every {} in the pattern is excluded",
+            class.describe()
+        ));
+    }
+
+    Ok(allowed)
+}
+
+// Alternatives separated by `|`, up to a closing parenthesis or the end of the pattern
+fn parse_alternatives(
+    chars: &mut Peekable<Chars>,
+    unquoted_letters: &mut Vec<String>,
+) -> Result<Vec<Vec<Element>>> {
+    let mut alternatives = vec![parse_sequence(chars, unquoted_letters)?];
+
+    while chars.peek() == Some(&'|') {
+        chars.next();
+        alternatives.push(parse_sequence(chars, unquoted_letters)?);
+    }
+
+    if alternatives.len() > 1 && alternatives.iter().any(Vec::is_empty) {
+        return Err(anyhow!(
+            "Empty alternative in pattern, use ? for an optional part"
+        ));
+    }
+
+    Ok(alternatives)
+}
+
+// Tokens and characters of fixed length the elements produce at most, counting words without
+// their own lengths as a single token
+fn size(elements: &[Element]) -> usize {
+    elements
+        .iter()
+        .map(|element| {
+            let single = match &element.token {
+                Token::Word { lengths } => lengths.map_or(1, |lengths| lengths.max),
+                Token::Number { digits } => digits.map_or(3, |digits| digits.max),
+                Token::Symbol | Token::Class(_) => 1,
+                Token::Literal(text) => text.chars().count(),
+                Token::Group(alternatives) => alternatives
+                    .iter()
+                    .map(|alternative| size(alternative))
+                    .max()
+                    .unwrap_or_default(),
+            };
+            single.saturating_mul(element.repeat.max)
+        })
+        .fold(0, usize::saturating_add)
+}
+
+fn modifier_warnings(elements: &[Element], warnings: &mut Vec<String>) {
+    for element in elements {
+        match (&element.token, element.casing) {
+            (Token::Group(alternatives), _) => alternatives
+                .iter()
+                .for_each(|alternative| modifier_warnings(alternative, warnings)),
+            (_, Some(casing)) => {
+                let token = Element {
+                    casing: None,
+                    optional: false,
+                    ..element.clone()
+                };
+                warnings.push(format!(
+                    "'{}' right after {} changes its case, quote it as \"{}'{}'\" if it is meant as literal text",
+                    casing, token, token, casing
+                ))
+            }
+            _ => {}
+        }
+    }
+}
+
+// Characters of classes keep their case under random case and only words are capitalized
+fn sequence_chars(
+    elements: &[Element],
+    words: &WordSource,
+    charset: &Charset,
+    case: PatternCase,
+) -> HashSet<char> {
+    let mut chars = HashSet::new();
+    for element in elements {
+        let (mut token_chars, case_changes): (HashSet<char>, bool) = match &element.token {
+            Token::Word { .. } => (words.chars(), case != PatternCase::Keep),
+            Token::Number { .. } => (('0'..='9').collect(), false),
+            Token::Symbol => (charset.symbols().iter().copied().collect(), false),
+            Token::Class(class) => (
+                class.chars().into_iter().collect(),
+                case == PatternCase::Lower,
+            ),
+            Token::Literal(text) => (
+                text.chars().collect(),
+                matches!(case, PatternCase::Random | PatternCase::Lower),
+            ),
+            Token::Group(alternatives) => (
+                alternatives
+                    .iter()
+                    .flat_map(|alternative| sequence_chars(alternative, words, charset, case))
+                    .collect(),
+                false,
+            ),
+        };
+        if case_changes || element.casing.is_some() {
+            let other_case: Vec<char> = token_chars
+                .iter()
+                .flat_map(|c| c.to_uppercase().chain(c.to_lowercase()))
+                .collect();
+            token_chars.extend(other_case);
+        }
+        chars.extend(token_chars);
+    }
+    chars
+}
+
+fn contains_words(elements: &[Element]) -> bool {
+    elements.iter().any(|element| match &element.token {
+        Token::Word { .. } => true,
+        Token::Group(alternatives) => alternatives
+            .iter()
+            .any(|alternative| contains_words(alternative)),
+        _ => false,
+    })
+}
+
+fn parse_sequence(
+    chars: &mut Peekable<Chars>,
+    unquoted_letters: &mut Vec<String>,
+) -> Result<Vec<Element>> {
+    let mut elements = Vec::new();
+    let mut letters = String::new();
+
+    while let Some(&c) = chars.peek() {
+        if c == '|' || c == ')' {
+            break;
+        }
+        chars.next();
+
+        let mut element = match c {
+            '"' | '\'' => Element {
+                token: Token::Literal(parse_quoted(chars, c)?),
+                repeat: parse_quantity(chars, "repeat count", 0)?,
+                casing: None,
+                optional: false,
+            },
+            '(' => {
+                let alternatives = parse_alternatives(chars, unquoted_letters)?;
+                if chars.next() != Some(')') {
+                    return Err(anyhow!("Unclosed ( in pattern"));
+                }
+                if alternatives.iter().all(Vec::is_empty) {
+                    return Err(anyhow!("Empty () in pattern"));
+                }
+                Element {
+                    token: Token::Group(alternatives),
+                    repeat: parse_quantity(chars, "repeat count", 0)?,
+                    casing: None,
+                    optional: false,
+                }
+            }
+            '?' => {
+                return Err(anyhow!(
+                    "? must follow a token or group in pattern, quote it for a literal '?'"
+                ));
+            }
+            c => parse_token(chars, c)?,
+        };
+
+        // Modifiers after single character literals stay literals, `W_W` is two lowercase words
+        // but `-_` is a dash and an underscore
+        let unquoted_literal = c != '"' && c != '\'' && matches!(element.token, Token::Literal(_));
+        if unquoted_literal && c.is_alphabetic() {
+            letters.push(c);
+        } else if !letters.is_empty() {
+            unquoted_letters.push(std::mem::take(&mut letters));
+        }
+        if !unquoted_literal
+            && let Some(casing) = chars.peek().and_then(|&c| Casing::from_modifier(c))
+        {
+            chars.next();
+            element.casing = Some(casing);
+        }
+        if chars.peek() == Some(&'?') {
+            chars.next();
+            element.optional = true;
+        }
+        elements.push(element);
+    }
+    if !letters.is_empty() {
+        unquoted_letters.push(letters);
+    }
+
+    Ok(elements)
+}
+
+fn parse_token(chars: &mut Peekable<Chars>, c: char) -> Result<Element> {
+    let (token, repeat) = match c {
+        'W' | 'w' => {
+            let lengths = if chars.peek() == Some(&'[') {
+                chars.next();
+                Some(parse_word_lengths(chars)?)
+            } else {
+                None
+            };
+            (
+                Token::Word { lengths },
+                parse_quantity(chars, "repeat count", 0)?,
+            )
+        }
+        'N' | 'n' => {
+            let digits = if chars.peek() == Some(&'{') {
+                Some(parse_quantity(chars, "digit count", 1)?)
+            } else {
+                None
+            };
+            (Token::Number { digits }, Quantity::ONE)
+        }
+        'C' | 'c' | 'S' | 's' => (Token::Symbol, parse_quantity(chars, "repeat count", 0)?),
+        '[' => (
+            Token::Class(CharClass::Custom(parse_set(chars)?)),
+            parse_quantity(chars, "repeat count", 0)?,
+        ),
+        c => match CharClass::from_token(c) {
+            Some(class) => (
+                Token::Class(class),
+                parse_quantity(chars, "repeat count", 0)?,
+            ),
+            None => (Token::Literal(c.to_string()), Quantity::ONE),
+        },
+    };
+
+    Ok(Element {
+        token,
+        repeat,
+        casing: None,
+        optional: false,
+    })
+}
+
+fn parse_quoted(chars: &mut Peekable<Chars>, quote: char) -> Result<String> {
+    let mut literal = String::new();
+
+    while let Some(c) = chars.next() {
+        if c == quote {
+            return Ok(literal);
+        } else if c == '\\' {
+            if let Some(escaped) = chars.next() {
+                literal.push(escaped);
+            }
+        } else {
+            literal.push(c);
+        }
+    }
+
+    let name = if quote == '"' { "double" } else { "single" };
+    Err(anyhow!("Unclosed {} quote in pattern", name))
+}
+
+// `[abc]` after the opening bracket, with ranges such as `a-z`. Duplicates are dropped so every
+// character of the set is equally likely
+fn parse_set(chars: &mut Peekable<Chars>) -> Result<Vec<char>> {
+    let mut set: Vec<char> = Vec::new();
+    let mut closed = false;
+
+    while let Some(c) = chars.next() {
+        let c = match c {
+            ']' => {
+                closed = true;
+                break;
+            }
+            '\\' => chars
+                .next()
+                .ok_or_else(|| anyhow!("Unclosed [ in pattern"))?,
+            c => c,
+        };
+
+        // A dash between two characters is a range, anywhere else it is a dash
+        let range_end = if chars.peek() == Some(&'-') {
+            let mut lookahead = chars.clone();
+            lookahead.next();
+            match lookahead.next() {
+                Some(end) if end != ']' => {
+                    chars.next();
+                    chars.next();
+                    Some(if end == '\\' {
+                        chars
+                            .next()
+                            .ok_or_else(|| anyhow!("Unclosed [ in pattern"))?
+                    } else {
+                        end
+                    })
+                }
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        let range = match range_end {
+            Some(end) if end < c => {
+                return Err(anyhow!("Invalid range {}-{} in pattern set", c, end));
+            }
+            Some(end) => c..=end,
+            None => c..=c,
+        };
+        for c in range {
+            if !set.contains(&c) {
+                set.push(c);
+            }
+        }
+    }
+
+    if !closed {
+        return Err(anyhow!("Unclosed [ in pattern"));
+    }
+    if set.is_empty() {
+        return Err(anyhow!("Empty [] in pattern"));
+    }
+
+    Ok(set)
+}
+
+// `{n}` or `{min,max}`, a single repetition when there are no braces
+fn parse_quantity(chars: &mut Peekable<Chars>, what: &str, lowest: usize) -> Result<Quantity> {
+    if chars.peek() != Some(&'{') {
+        return Ok(Quantity::ONE);
+    }
+    chars.next();
+
+    let body = take_until(chars, '}')
+        .ok_or_else(|| anyhow!("Unclosed {{ in pattern, expected {{n}} or {{min,max}}"))?;
+    let quantity = match body.split_once(',') {
+        Some((min, max)) => Quantity {
+            min: parse_count(min, &body)?,
+            max: parse_count(max, &body)?,
+        },
+        None => {
+            let count = parse_count(&body, &body)?;
+            Quantity {
+                min: count,
+                max: count,
+            }
+        }
+    };
+
+    check_range(quantity, what, lowest, &format!("{{{}}}", body))
+}
+
+// `[n]` or `[min-max]` after the opening bracket
+fn parse_word_lengths(chars: &mut Peekable<Chars>) -> Result<Quantity> {
+    let body = take_until(chars, ']')
+        .ok_or_else(|| anyhow!("Unclosed [ in pattern, expected W[n] or W[min-max]"))?;
+    let lengths = match body.split_once('-') {
+        Some((min, max)) => Quantity {
+            min: parse_count(min, &body)?,
+            max: parse_count(max, &body)?,
+        },
+        None => {
+            let length = parse_count(&body, &body)?;
+            Quantity {
+                min: length,
+                max: length,
+            }
+        }
+    };
+
+    check_range(lengths, "word length", 1, &format!("[{}]", body))
+}
+
+fn take_until(chars: &mut Peekable<Chars>, end: char) -> Option<String> {
+    let mut body = String::new();
+    for c in chars.by_ref() {
+        if c == end {
+            return Some(body);
+        }
+        body.push(c);
+    }
+    None
+}
+
+fn parse_count(count: &str, body: &str) -> Result<usize> {
+    count
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("Invalid number '{}' in pattern quantity '{}'", count, body))
+}
+
+fn check_range(quantity: Quantity, what: &str, lowest: usize, text: &str) -> Result<Quantity> {
+    if quantity.max > MAX_QUANTITY {
+        return Err(anyhow!(
+            "The {} in {} must be at most {}",
+            what,
+            text,
+            MAX_QUANTITY
+        ));
+    }
+    if quantity.min > quantity.max {
+        return Err(anyhow!(
+            "The minimum {} in {} is greater than the maximum",
+            what,
+            text
+        ));
+    }
+    if quantity.min < lowest {
+        return Err(anyhow!(
+            "The {} in {} must be at least {}",
+            what,
+            text,
+            lowest
+        ));
+    }
+
+    Ok(quantity)
+}
+
+fn check_literal(charset: &Charset, literal: &str) -> Result<()> {
+    match literal.chars().find(|c| !charset.allows(*c)) {
+        Some(c) => Err(anyhow!(
+            "Pattern literal '{}' contains the excluded character '{}'",
+            literal,
+            c
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn generate(pattern: &str) -> String {
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        Pattern::parse(pattern)
+            .unwrap()
+            .generate(
+                &mut rng,
+                &WordSource::Generated,
+                &Charset::default(),
+                4,
+                6,
+                PatternCase::Keep,
+            )
+            .unwrap()
+            .password
+    }
+
+    #[test]
+    fn lowercase_class_letters_are_literals() {
+        assert!(generate("W-dev-N").contains("-dev-"));
+        // `c` is a special character like `C`, the rest of the word is literal text
+        assert_eq!(&generate("cool-W")[1..5], "ool-");
+        assert_eq!(
+            Pattern::parse("W-dev-N").unwrap().unquoted_letters(),
+            ["dev"]
+        );
+    }
+
+    #[test]
+    fn warns_about_modifiers_right_after_a_token() {
+        let warnings = Pattern::parse("W_N").unwrap().modifier_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("W'_'"));
+
+        assert!(
+            Pattern::parse("W'_'N")
+                .unwrap()
+                .modifier_warnings()
+                .is_empty()
+        );
+        assert!(Pattern::parse("-_").unwrap().modifier_warnings().is_empty());
+    }
+
+    #[test]
+    fn quantities_are_capped() {
+        assert!(Pattern::parse("D{1024}").is_ok());
+        for pattern in [
+            "W{999999999}",
+            "N{999999999}",
+            "W[2000]",
+            "D{1024}D",
+            "(W{40}){40}",
+        ] {
+            assert!(Pattern::parse(pattern).is_err(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn quoted_literals_repeat() {
+        assert_eq!(generate("'x'{3}"), "xxx");
+        assert_eq!(Pattern::parse("'x'{3}").unwrap().to_string(), "'x'{3}");
+    }
+}
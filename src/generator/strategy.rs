@@ -0,0 +1,111 @@
+use crate::generator::charset::Charset;
+use crate::generator::passphrase::{PassphraseOptions, generate_passphrase};
+use crate::generator::password::{generate_password_with_target_length, random_chars};
+use crate::generator::pattern::{Pattern, PatternCase};
+use crate::generator::wordlist::WordSource;
+use crate::models::generated_password::GeneratedPassword;
+use anyhow::Result;
+use rand::{CryptoRng, RngCore};
+
+/// A way of generating passwords.
+///
+/// `min_length` and `max_length` bound the whole password for [`DefaultGenerator`] and
+/// [`RandomGenerator`], and each word for [`PatternGenerator`] and [`PassphraseGenerator`].
+pub trait Generator {
+    fn generate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        min_length: usize,
+        max_length: usize,
+    ) -> Result<GeneratedPassword>;
+}
+
+/// Words, a number and special characters shuffled together, e.g. `siond322#`
+pub struct DefaultGenerator {
+    pub charset: Charset,
+    pub capitalize: bool,
+}
+
+impl Generator for DefaultGenerator {
+    fn generate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        min_length: usize,
+        max_length: usize,
+    ) -> Result<GeneratedPassword> {
+        generate_password_with_target_length(
+            rng,
+            &self.charset,
+            min_length,
+            max_length,
+            self.capitalize,
+        )
+    }
+}
+
+/// Random letters, digits and special characters, e.g. `np#1rrM<v_`
+pub struct RandomGenerator {
+    pub charset: Charset,
+}
+
+impl Generator for RandomGenerator {
+    fn generate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        min_length: usize,
+        max_length: usize,
+    ) -> Result<GeneratedPassword> {
+        Ok(random_chars(rng, &self.charset, min_length, max_length))
+    }
+}
+
+/// Passwords following a pattern such as `W-N-C`, see [`Pattern`]
+pub struct PatternGenerator {
+    pub pattern: Pattern,
+    pub word_source: WordSource,
+    pub charset: Charset,
+    pub case: PatternCase,
+}
+
+impl Generator for PatternGenerator {
+    fn generate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        min_length: usize,
+        max_length: usize,
+    ) -> Result<GeneratedPassword> {
+        self.pattern.generate(
+            rng,
+            &self.word_source,
+            &self.charset,
+            min_length,
+            max_length,
+            self.case,
+        )
+    }
+}
+
+/// Diceware style passphrases, e.g. `ranor-pressian-hesilet-dener`
+pub struct PassphraseGenerator {
+    pub options: PassphraseOptions,
+    pub word_source: WordSource,
+    pub charset: Charset,
+}
+
+impl Generator for PassphraseGenerator {
+    fn generate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        min_length: usize,
+        max_length: usize,
+    ) -> Result<GeneratedPassword> {
+        generate_passphrase(
+            rng,
+            &self.options,
+            &self.word_source,
+            &self.charset,
+            min_length,
+            max_length,
+        )
+    }
+}
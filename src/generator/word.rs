@@ -1,8 +1,18 @@
+use crate::generator::entropy::choice_bits;
 use crate::helper::output::is_clean_break;
 use crate::helper::phonetics::{contains_vowel, ends_with_vowel, get_phonetics};
 use rand::Rng;
 use std::collections::{HashMap, HashSet};
 
+// Number of candidate words generated before the best scoring one is picked
+const CANDIDATE_COUNT: usize = 15;
+
+// Bits credited per letter of a synthesized word. The generators alternate between
+// consonant and vowel clusters, so we credit the smallest alphabet they draw from (a, e, i, o, u)
+const SYNTHESIZED_BITS_PER_LETTER: f64 = 2.321928094887362;
+
+/// Generate a pronounceable, English sounding word between `min_length` and `max_length` letters.
+/// Several candidates are synthesized and the one that scores as the most English like wins.
 pub fn generate_random_word(min_length: usize, max_length: usize, rng: &mut impl Rng) -> String {
     if min_length <= 3 && max_length <= 3 {
         let word = generate_short_english_word(max_length, rng);
@@ -14,7 +24,7 @@ pub fn generate_random_word(min_length: usize, max_length: usize, rng: &mut impl
         };
     }
 
-    let candidates = (0..15)
+    let candidates = (0..CANDIDATE_COUNT)
         .map(|_| {
             let algorithm_choice = rng.random_range(0..4);
 
@@ -45,6 +55,26 @@ pub fn generate_random_word(min_length: usize, max_length: usize, rng: &mut impl
 
     result
 }
+
+/// Estimate the entropy in bits of a word of `word_length` returned by `generate_random_word`.
+/// Short words are picked from fixed lists, longer words are the best of `CANDIDATE_COUNT`
+/// synthesized candidates, and picking the best one can cost up to log2(CANDIDATE_COUNT) bits.
+pub fn estimate_word_entropy(word_length: usize, min_length: usize, max_length: usize) -> f64 {
+    if min_length <= 3 && max_length <= 3 {
+        return match word_length {
+            1 => choice_bits(4),
+            2 => choice_bits(distinct_count(&TWO_LETTER_WORDS)),
+            _ => choice_bits(distinct_count(&THREE_LETTER_WORDS)),
+        };
+    }
+
+    (word_length as f64 * SYNTHESIZED_BITS_PER_LETTER - choice_bits(CANDIDATE_COUNT)).max(0.0)
+}
+
+fn distinct_count(words: &[&str]) -> usize {
+    words.iter().collect::<HashSet<_>>().len()
+}
+
 fn generate_exact_length_word(min_length: usize, max_length: usize, rng: &mut impl Rng) -> String {
     let target_length = if min_length == max_length {
         min_length
@@ -524,7 +554,7 @@ fn create_syllable(pattern: &str, rng: &mut impl Rng) -> String {
                 if syllable
                     .chars()
                     .last()
-                    .map_or(false, |last| !"aeiou".contains(last))
+                    .is_some_and(|last| !"aeiou".contains(last))
                 {
                     let single_consonants = [
                         "b", "c", "d", "f", "g", "h", "j", "k", "l", "m", "n", "p", "r", "s", "t",
@@ -540,9 +570,9 @@ fn create_syllable(pattern: &str, rng: &mut impl Rng) -> String {
                     ];
 
                     if rng.random_bool(0.7) {
-                        syllable.push_str(&consonants[0..18][rng.random_range(0..18)]);
+                        syllable.push_str(consonants[0..18][rng.random_range(0..18)]);
                     } else {
-                        syllable.push_str(&consonants[18..][rng.random_range(0..16)]);
+                        syllable.push_str(consonants[18..][rng.random_range(0..16)]);
                     }
                 }
             }
@@ -552,9 +582,9 @@ fn create_syllable(pattern: &str, rng: &mut impl Rng) -> String {
                 ];
 
                 if rng.random_bool(0.85) {
-                    syllable.push_str(&vowels[0..10][rng.random_range(0..10)]);
+                    syllable.push_str(vowels[0..10][rng.random_range(0..10)]);
                 } else {
-                    syllable.push_str(&vowels[10..][rng.random_range(0..5)]);
+                    syllable.push_str(vowels[10..][rng.random_range(0..5)]);
                 }
             }
             _ => {}
@@ -713,9 +743,9 @@ fn score_english_word(word: &str) -> f64 {
     let vowel_count = word.chars().filter(|&c| "aeiou".contains(c)).count() as f64;
     let ratio = vowel_count / word.len() as f64;
 
-    if ratio < 0.25 || ratio > 0.55 {
+    if !(0.25..=0.55).contains(&ratio) {
         score -= 1.5;
-    } else if ratio >= 0.38 && ratio <= 0.42 {
+    } else if (0.38..=0.42).contains(&ratio) {
         score += 0.5;
     }
 
@@ -826,46 +856,44 @@ fn ensure_minimum_length(word: &mut String, min_length: usize, rng: &mut impl Rn
         }
     }
 }
+
+const TWO_LETTER_WORDS: [&str; 93] = [
+    "an", "as", "at", "be", "by", "do", "go", "he", "hi", "if", "in", "is", "it", "me", "my", "no",
+    "of", "on", "or", "so", "to", "up", "us", "we", "am", "an", "ba", "bo", "ca", "co", "da", "de",
+    "di", "du", "fa", "fe", "fi", "fo", "ga", "ge", "ha", "ho", "ja", "jo", "ka", "ke", "ki", "la",
+    "le", "li", "lo", "ma", "mi", "mo", "mu", "na", "ne", "ni", "nu", "pa", "pe", "pi", "po", "pu",
+    "ra", "re", "ri", "ro", "ru", "sa", "se", "si", "su", "ta", "te", "ti", "to", "tu", "va", "ve",
+    "vi", "vo", "wa", "we", "wi", "wo", "ya", "ye", "yo", "yu", "za", "ze", "zo",
+];
+
+const THREE_LETTER_WORDS: [&str; 223] = [
+    "and", "art", "ask", "bad", "bag", "bar", "bat", "bed", "bee", "big", "bit", "box", "boy",
+    "bug", "bus", "but", "buy", "can", "cap", "car", "cat", "cow", "cry", "cup", "cut", "dad",
+    "day", "did", "dig", "dog", "dot", "dry", "due", "ear", "eat", "egg", "end", "eye", "fan",
+    "far", "fat", "few", "fig", "fit", "fix", "fly", "for", "fox", "fun", "gap", "gas", "get",
+    "got", "gum", "gun", "gym", "had", "ham", "has", "hat", "her", "hey", "him", "hip", "his",
+    "hit", "hop", "hot", "how", "hub", "hug", "hut", "ice", "ill", "ink", "jar", "jaw", "jet",
+    "job", "jog", "joy", "key", "kid", "kit", "lab", "lag", "lap", "law", "lay", "leg", "let",
+    "lid", "lie", "lip", "lit", "log", "lot", "low", "mad", "man", "map", "mat", "may", "men",
+    "met", "mix", "mom", "mop", "mud", "mug", "nap", "net", "new", "nil", "nod", "not", "now",
+    "nut", "odd", "off", "oil", "old", "one", "our", "out", "owe", "own", "pad", "pan", "paw",
+    "pay", "pen", "pet", "pie", "pig", "pin", "pit", "pop", "pot", "put", "rad", "rag", "ran",
+    "rap", "rat", "raw", "red", "rib", "rid", "rim", "rip", "rob", "rod", "rot", "row", "rub",
+    "rug", "run", "sad", "saw", "say", "sea", "see", "set", "she", "shy", "sin", "sip", "sir",
+    "sit", "six", "ski", "sky", "sly", "son", "spy", "sum", "sun", "tab", "tag", "tan", "tap",
+    "tar", "tax", "tea", "ten", "the", "tie", "tin", "tip", "toe", "ton", "too", "top", "toy",
+    "try", "tub", "two", "use", "van", "vat", "vet", "via", "vow", "war", "was", "wax", "way",
+    "web", "wed", "wet", "who", "why", "wig", "win", "wit", "won", "wow", "yes", "yet", "you",
+    "zip", "zoo",
+];
+
 fn generate_short_english_word(length: usize, rng: &mut impl Rng) -> String {
     match length {
         1 => ["a", "a", "i", "o", "e"][rng.random_range(0..5)].to_string(),
-        2 => {
-            let options = [
-                "an", "as", "at", "be", "by", "do", "go", "he", "hi", "if", "in", "is", "it", "me",
-                "my", "no", "of", "on", "or", "so", "to", "up", "us", "we", "am", "an", "ba", "bo",
-                "ca", "co", "da", "de", "di", "du", "fa", "fe", "fi", "fo", "ga", "ge", "ha", "ho",
-                "ja", "jo", "ka", "ke", "ki", "la", "le", "li", "lo", "ma", "mi", "mo", "mu", "na",
-                "ne", "ni", "nu", "pa", "pe", "pi", "po", "pu", "ra", "re", "ri", "ro", "ru", "sa",
-                "se", "si", "su", "ta", "te", "ti", "to", "tu", "va", "ve", "vi", "vo", "wa", "we",
-                "wi", "wo", "ya", "ye", "yo", "yu", "za", "ze", "zo",
-            ];
-            options[rng.random_range(0..options.len())].to_string()
-        }
+        2 => TWO_LETTER_WORDS[rng.random_range(0..TWO_LETTER_WORDS.len())].to_string(),
         3 => {
-            let real_words = [
-                "and", "art", "ask", "bad", "bag", "bar", "bat", "bed", "bee", "big", "bit", "box",
-                "boy", "bug", "bus", "but", "buy", "can", "cap", "car", "cat", "cow", "cry", "cup",
-                "cut", "dad", "day", "did", "dig", "dog", "dot", "dry", "due", "ear", "eat", "egg",
-                "end", "eye", "fan", "far", "fat", "few", "fig", "fit", "fix", "fly", "for", "fox",
-                "fun", "gap", "gas", "get", "got", "gum", "gun", "gym", "had", "ham", "has", "hat",
-                "her", "hey", "him", "hip", "his", "hit", "hop", "hot", "how", "hub", "hug", "hut",
-                "ice", "ill", "ink", "jar", "jaw", "jet", "job", "jog", "joy", "key", "kid", "kit",
-                "lab", "lag", "lap", "law", "lay", "leg", "let", "lid", "lie", "lip", "lit", "log",
-                "lot", "low", "mad", "man", "map", "mat", "may", "men", "met", "mix", "mom", "mop",
-                "mud", "mug", "nap", "net", "new", "nil", "nod", "not", "now", "nut", "odd", "off",
-                "oil", "old", "one", "our", "out", "owe", "own", "pad", "pan", "paw", "pay", "pen",
-                "pet", "pie", "pig", "pin", "pit", "pop", "pot", "put", "rad", "rag", "ran", "rap",
-                "rat", "raw", "red", "rib", "rid", "rim", "rip", "rob", "rod", "rot", "row", "rub",
-                "rug", "run", "sad", "saw", "say", "sea", "see", "set", "she", "shy", "sin", "sip",
-                "sir", "sit", "six", "ski", "sky", "sly", "son", "spy", "sum", "sun", "tab", "tag",
-                "tan", "tap", "tar", "tax", "tea", "ten", "the", "tie", "tin", "tip", "toe", "ton",
-                "too", "top", "toy", "try", "tub", "two", "use", "van", "vat", "vet", "via", "vow",
-                "war", "was", "wax", "way", "web", "wed", "wet", "who", "why", "wig", "win", "wit",
-                "won", "wow", "yes", "yet", "you", "zip", "zoo",
-            ];
-
             if rng.random_bool(0.8) {
-                real_words[rng.random_range(0..real_words.len())].to_string()
+                THREE_LETTER_WORDS[rng.random_range(0..THREE_LETTER_WORDS.len())].to_string()
             } else {
                 let patterns = ["CVC", "VCC", "CCV"];
                 let pattern = patterns[rng.random_range(0..patterns.len())];
@@ -877,14 +905,13 @@ fn generate_short_english_word(length: usize, rng: &mut impl Rng) -> String {
 }
 
 fn to_vector_str(array: Vec<(&str, usize)>) -> Vec<&str> {
-    let dict = array
+    array
         .iter()
-        .flat_map(|(p, w)| std::iter::repeat(*p).take(*w))
-        .collect::<Vec<_>>();
-
-    dict
+        .flat_map(|(p, w)| std::iter::repeat_n(*p, *w))
+        .collect::<Vec<_>>()
 }
 
+/// Uppercase the first letter
 pub fn capitalize(s: &str) -> String {
     let mut c = s.chars();
     match c.next() {
@@ -0,0 +1,245 @@
+use crate::generator::charset::Charset;
+use crate::generator::entropy::choice_bits;
+use crate::generator::word::{estimate_word_entropy, generate_random_word};
+use crate::models::pattern_explanation::Bounds;
+use anyhow::{Context, Result, anyhow};
+use rand::Rng;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+// Attempts at synthesizing a word without excluded characters before giving up
+const WORD_ATTEMPTS: usize = 1000;
+
+#[cfg(feature = "eff-wordlists")]
+const EFF_LARGE_WORDLIST: &str = include_str!("../../assets/wordlists/eff_large_wordlist.txt");
+#[cfg(feature = "eff-wordlists")]
+const EFF_SHORT_WORDLIST: &str = include_str!("../../assets/wordlists/eff_short_wordlist.txt");
+
+/// A list of distinct dictionary words to pick from
+pub struct Wordlist {
+    words: Vec<String>,
+}
+
+impl Wordlist {
+    /// Parse a wordlist with one word per line. Lines in diceware format (`11111<TAB>abacus`)
+    /// use their last column, blank lines and duplicates are skipped.
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut seen = HashSet::new();
+        let words: Vec<String> = content
+            .lines()
+            .filter_map(|line| line.split_whitespace().last())
+            .filter(|word| seen.insert(*word))
+            .map(str::to_string)
+            .collect();
+
+        if words.is_empty() {
+            return Err(anyhow!("Wordlist does not contain any words"));
+        }
+
+        Ok(Self { words })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read wordlist {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid wordlist {}", path.display()))
+    }
+
+    /// Load one of the wordlists embedded in the binary (`eff-large` or `eff-short`)
+    pub fn builtin(name: &str) -> Option<Result<Self>> {
+        match name {
+            #[cfg(feature = "eff-wordlists")]
+            "eff-large" => Some(Self::parse(EFF_LARGE_WORDLIST)),
+            #[cfg(feature = "eff-wordlists")]
+            "eff-short" => Some(Self::parse(EFF_SHORT_WORDLIST)),
+            #[cfg(not(feature = "eff-wordlists"))]
+            "eff-large" | "eff-short" => Some(Err(anyhow!(
+                "Built-in wordlists are not available, build pw with the `eff-wordlists` feature"
+            ))),
+            _ => None,
+        }
+    }
+
+    /// Load a built-in wordlist by name, otherwise read the wordlist file at that path
+    pub fn load(name_or_path: &str) -> Result<Self> {
+        Self::builtin(name_or_path).unwrap_or_else(|| Self::from_file(Path::new(name_or_path)))
+    }
+
+    /// Every character that occurs in the words
+    pub fn chars(&self) -> HashSet<char> {
+        self.words.iter().flat_map(|word| word.chars()).collect()
+    }
+
+    /// Pick a word with a length in the given range and without excluded characters uniformly,
+    /// returning it with its entropy
+    pub fn random_word(
+        &self,
+        min_length: usize,
+        max_length: usize,
+        charset: &Charset,
+        rng: &mut impl Rng,
+    ) -> Result<(String, f64)> {
+        let candidates = self.candidates(min_length, max_length, charset)?;
+
+        let word = candidates[rng.random_range(0..candidates.len())].clone();
+        Ok((word, choice_bits(candidates.len())))
+    }
+
+    /// Number of words `random_word` picks from and the lengths of the shortest and longest
+    pub fn candidate_lengths(
+        &self,
+        min_length: usize,
+        max_length: usize,
+        charset: &Charset,
+    ) -> Result<(usize, usize, usize)> {
+        let lengths: Vec<usize> = self
+            .candidates(min_length, max_length, charset)?
+            .iter()
+            .map(|word| word.chars().count())
+            .collect();
+        let shortest = lengths.iter().min().copied().unwrap_or_default();
+        let longest = lengths.iter().max().copied().unwrap_or_default();
+
+        Ok((lengths.len(), shortest, longest))
+    }
+
+    fn candidates(
+        &self,
+        min_length: usize,
+        max_length: usize,
+        charset: &Charset,
+    ) -> Result<Vec<&String>> {
+        let candidates: Vec<&String> = self
+            .words
+            .iter()
+            .filter(|word| (min_length..=max_length).contains(&word.chars().count()))
+            .filter(|word| charset.allows_all(word))
+            .collect();
+
+        if candidates.is_empty() {
+            return Err(anyhow!(
+                "Wordlist has no words between {} and {} characters without excluded characters",
+                min_length,
+                max_length
+            ));
+        }
+
+        Ok(candidates)
+    }
+}
+
+/// Where the words of patterns and passphrases come from
+pub enum WordSource {
+    /// Pronounceable words synthesized by `generate_random_word`
+    Generated,
+    /// Words picked from a dictionary
+    List(Wordlist),
+}
+
+impl WordSource {
+    /// Produce a word with a length in the given range and without excluded characters,
+    /// returning it with its entropy
+    pub fn random_word(
+        &self,
+        min_length: usize,
+        max_length: usize,
+        charset: &Charset,
+        rng: &mut impl Rng,
+    ) -> Result<(String, f64)> {
+        match self {
+            WordSource::Generated => {
+                for _ in 0..WORD_ATTEMPTS {
+                    let word = generate_random_word(min_length, max_length, rng);
+                    if charset.allows_all(&word) {
+                        let entropy_bits =
+                            estimate_word_entropy(word.len(), min_length, max_length);
+                        return Ok((word, entropy_bits));
+                    }
+                }
+
+                Err(anyhow!(
+                    "Could not generate a word without the excluded characters"
+                ))
+            }
+            WordSource::List(wordlist) => {
+                wordlist.random_word(min_length, max_length, charset, rng)
+            }
+        }
+    }
+
+    /// Every character the words can contain, before exclusions
+    pub fn chars(&self) -> HashSet<char> {
+        match self {
+            WordSource::Generated => ('a'..='z').collect(),
+            WordSource::List(wordlist) => wordlist.chars(),
+        }
+    }
+
+    /// Lengths and entropy of the words `random_word` produces with these options
+    pub fn word_bounds(
+        &self,
+        min_length: usize,
+        max_length: usize,
+        charset: &Charset,
+    ) -> Result<Bounds> {
+        match self {
+            WordSource::Generated => {
+                let mut entropy = (min_length..=max_length)
+                    .map(|length| estimate_word_entropy(length, min_length, max_length));
+                let first = entropy.next().unwrap_or_default();
+                let (min_entropy_bits, max_entropy_bits) = entropy
+                    .fold((first, first), |(min, max), bits| {
+                        (min.min(bits), max.max(bits))
+                    });
+
+                Ok(Bounds {
+                    min_length,
+                    max_length,
+                    min_entropy_bits,
+                    max_entropy_bits,
+                })
+            }
+            WordSource::List(wordlist) => {
+                let (count, shortest, longest) =
+                    wordlist.candidate_lengths(min_length, max_length, charset)?;
+
+                Ok(Bounds {
+                    min_length: shortest,
+                    max_length: longest,
+                    min_entropy_bits: choice_bits(count),
+                    max_entropy_bits: choice_bits(count),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn parses_diceware_lists_and_picks_from_the_candidates() {
+        let wordlist = Wordlist::parse(
+            "11111 abacus\n11112 café\n\n11113 abacus\n   \n11114 zebra\n11115 do\n",
+        )
+        .unwrap();
+        assert_eq!(wordlist.words, ["abacus", "café", "zebra", "do"]);
+
+        // "do" is too short, leaving three candidates of 4 to 6 characters
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        let mut picked = HashSet::new();
+        for _ in 0..100 {
+            let (word, entropy_bits) = wordlist
+                .random_word(4, 6, &Charset::default(), &mut rng)
+                .unwrap();
+            assert_eq!(entropy_bits, 3f64.log2());
+            picked.insert(word);
+        }
+        assert_eq!(picked.len(), 3);
+        assert!(!picked.contains("do"));
+    }
+}
@@ -1,11 +1,192 @@
-use crate::cli::cli::EncodingFormat;
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow};
+use argon2::password_hash::{PasswordHasher, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
 use base64::{Engine, engine::general_purpose::STANDARD};
 use bcrypt::hash_with_salt;
+use md5::Md5;
+use pwhash::{HashSetup, sha256_crypt, sha512_crypt};
 use rand::{Rng, rng};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
 use sha2::{Digest, Sha256, Sha512};
+use std::ops::RangeInclusive;
 
+// Rounds accepted by sha256-crypt and sha512-crypt
+const CRYPT_ROUNDS: RangeInclusive<u32> = 1000..=999_999_999;
+
+// Costs accepted by bcrypt
+const BCRYPT_COST: RangeInclusive<u32> = 4..=31;
+
+// Alphabet of the crypt(3) base64 variant used by apr1
+const CRYPT_ALPHABET: &[u8; 64] =
+    b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Ways a generated password can be encoded or hashed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum EncodingFormat {
+    None,
+    Base64,
+    Url,
+    Sha256,
+    Sha512,
+    /// bcrypt hash for htpasswd files, `$2b$...`
+    Htpasswd,
+    /// Base64 SHA-1 htpasswd hash, `{SHA}...`. Only for legacy servers
+    HtpasswdSha,
+    /// Apache MD5 htpasswd hash, `$apr1$...`
+    HtpasswdApr1,
+    /// Argon2id PHC string, `$argon2id$...`
+    Argon2id,
+    /// scrypt PHC string, `$scrypt$...`
+    Scrypt,
+    /// PBKDF2-HMAC-SHA256 PHC string, `$pbkdf2-sha256$...`
+    Pbkdf2,
+    /// SHA-512 based crypt(3) hash, `$6$...`
+    Sha512Crypt,
+    /// SHA-256 based crypt(3) hash, `$5$...`
+    Sha256Crypt,
+}
+
+impl EncodingFormat {
+    /// The name used on the command line and as key in structured output
+    pub fn name(&self) -> &'static str {
+        match self {
+            EncodingFormat::None => "none",
+            EncodingFormat::Base64 => "base64",
+            EncodingFormat::Url => "url",
+            EncodingFormat::Sha256 => "sha256",
+            EncodingFormat::Sha512 => "sha512",
+            EncodingFormat::Htpasswd => "htpasswd",
+            EncodingFormat::HtpasswdSha => "htpasswd-sha",
+            EncodingFormat::HtpasswdApr1 => "htpasswd-apr1",
+            EncodingFormat::Argon2id => "argon2id",
+            EncodingFormat::Scrypt => "scrypt",
+            EncodingFormat::Pbkdf2 => "pbkdf2",
+            EncodingFormat::Sha512Crypt => "sha512-crypt",
+            EncodingFormat::Sha256Crypt => "sha256-crypt",
+        }
+    }
+
+    /// Whether the encoding is a hash that can go into an htpasswd file
+    pub fn is_htpasswd(&self) -> bool {
+        matches!(
+            self,
+            EncodingFormat::Htpasswd | EncodingFormat::HtpasswdSha | EncodingFormat::HtpasswdApr1
+        )
+    }
+}
+
+/// Cost parameters of the slow password hashes. The defaults follow the OWASP recommendations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HashCosts {
+    pub argon2_memory_kib: u32,
+    pub argon2_iterations: u32,
+    pub argon2_parallelism: u32,
+    pub scrypt_log_n: u8,
+    pub scrypt_r: u32,
+    pub scrypt_p: u32,
+    pub pbkdf2_rounds: u32,
+    pub crypt_rounds: u32,
+    pub bcrypt_cost: u32,
+}
+
+impl Default for HashCosts {
+    fn default() -> Self {
+        Self {
+            argon2_memory_kib: 19 * 1024,
+            argon2_iterations: 2,
+            argon2_parallelism: 1,
+            scrypt_log_n: 17,
+            scrypt_r: 8,
+            scrypt_p: 1,
+            pbkdf2_rounds: 600_000,
+            crypt_rounds: 5000,
+            bcrypt_cost: 10,
+        }
+    }
+}
+
+impl HashCosts {
+    /// The cost parameters `format` is hashed with, `None` for encodings without a cost
+    pub fn parameters(&self, format: &EncodingFormat) -> Option<Vec<(&'static str, u32)>> {
+        match format {
+            EncodingFormat::Argon2id => Some(vec![
+                ("memory_kib", self.argon2_memory_kib),
+                ("iterations", self.argon2_iterations),
+                ("parallelism", self.argon2_parallelism),
+            ]),
+            EncodingFormat::Scrypt => Some(vec![
+                ("log_n", u32::from(self.scrypt_log_n)),
+                ("r", self.scrypt_r),
+                ("p", self.scrypt_p),
+            ]),
+            EncodingFormat::Pbkdf2 => Some(vec![("rounds", self.pbkdf2_rounds)]),
+            EncodingFormat::Htpasswd => Some(vec![("cost", self.bcrypt_cost)]),
+            EncodingFormat::Sha512Crypt | EncodingFormat::Sha256Crypt => {
+                Some(vec![("rounds", self.crypt_rounds)])
+            }
+            _ => None,
+        }
+    }
+
+    /// Check the parameters before spending time on hashing
+    pub fn validate(&self) -> Result<()> {
+        self.argon2_params()?;
+        self.scrypt_params()?;
+
+        if self.pbkdf2_rounds == 0 {
+            return Err(anyhow!("PBKDF2 needs at least one round"));
+        }
+
+        crypt_setup(self)?;
+
+        if !BCRYPT_COST.contains(&self.bcrypt_cost) {
+            return Err(anyhow!(
+                "bcrypt cost must be between {} and {}",
+                BCRYPT_COST.start(),
+                BCRYPT_COST.end()
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn argon2_params(&self) -> Result<Params> {
+        Params::new(
+            self.argon2_memory_kib,
+            self.argon2_iterations,
+            self.argon2_parallelism,
+            None,
+        )
+        .map_err(|e| anyhow!("Invalid Argon2 parameters: {}", e))
+    }
+
+    fn scrypt_params(&self) -> Result<scrypt::Params> {
+        scrypt::Params::new(
+            self.scrypt_log_n,
+            self.scrypt_r,
+            self.scrypt_p,
+            scrypt::Params::RECOMMENDED_LEN,
+        )
+        .map_err(|e| anyhow!("Invalid scrypt parameters: {}", e))
+    }
+}
+
+/// Encode a password. SHA hashes are hex encoded, htpasswd is a bcrypt hash with cost 10.
+/// Slow hashes use the default [`HashCosts`]
 pub fn encode_password(password: &str, format: &EncodingFormat) -> Result<String> {
+    encode_password_with(password, format, &HashCosts::default())
+}
+
+/// Encode a password, hashing it with the given cost parameters
+pub fn encode_password_with(
+    password: &str,
+    format: &EncodingFormat,
+    costs: &HashCosts,
+) -> Result<String> {
     match format {
         EncodingFormat::None => Ok(password.to_string()),
 
@@ -27,11 +208,93 @@ pub fn encode_password(password: &str, format: &EncodingFormat) -> Result<String
             Ok(hex::encode(result))
         }
 
-        EncodingFormat::Htpasswd => {
-            let hashed = encode_htpasswd_bcrypt(password, 10)?;
-            Ok(format!("{}", hashed))
+        EncodingFormat::Htpasswd => encode_htpasswd_bcrypt(password, costs.bcrypt_cost),
+
+        EncodingFormat::HtpasswdSha => {
+            let digest = <Sha1 as sha1::Digest>::digest(password.as_bytes());
+            Ok(format!("{{SHA}}{}", STANDARD.encode(digest)))
+        }
+
+        EncodingFormat::HtpasswdApr1 => {
+            let mut salt = [0u8; 8];
+            for byte in &mut salt {
+                *byte = CRYPT_ALPHABET[rng().random_range(0..CRYPT_ALPHABET.len())];
+            }
+            Ok(encode_htpasswd_apr1(password.as_bytes(), &salt))
+        }
+
+        EncodingFormat::Argon2id => {
+            let salt = random_salt()?;
+            let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, costs.argon2_params()?);
+            let hash = argon2
+                .hash_password(password.as_bytes(), &salt)
+                .map_err(|e| anyhow!("Failed to hash password: {}", e))?;
+            Ok(hash.to_string())
         }
+
+        EncodingFormat::Scrypt => {
+            let salt = random_salt()?;
+            let hash = scrypt::Scrypt
+                .hash_password_customized(
+                    password.as_bytes(),
+                    None,
+                    None,
+                    costs.scrypt_params()?,
+                    &salt,
+                )
+                .map_err(|e| anyhow!("Failed to hash password: {}", e))?;
+            Ok(hash.to_string())
+        }
+
+        EncodingFormat::Pbkdf2 => {
+            let salt = random_salt()?;
+            let params = pbkdf2::Params {
+                rounds: costs.pbkdf2_rounds,
+                output_length: 32,
+            };
+            let hash = pbkdf2::Pbkdf2
+                .hash_password_customized(
+                    password.as_bytes(),
+                    Some(pbkdf2::Algorithm::Pbkdf2Sha256.ident()),
+                    None,
+                    params,
+                    &salt,
+                )
+                .map_err(|e| anyhow!("Failed to hash password: {}", e))?;
+            Ok(hash.to_string())
+        }
+
+        EncodingFormat::Sha512Crypt => sha512_crypt::hash_with(crypt_setup(costs)?, password)
+            .context("Failed to hash password"),
+
+        // Deprecated in favour of sha512-crypt, but still what some systems expect
+        #[allow(deprecated)]
+        EncodingFormat::Sha256Crypt => sha256_crypt::hash_with(crypt_setup(costs)?, password)
+            .context("Failed to hash password"),
+    }
+}
+
+// 16 random bytes, the salt length recommended for all of the PHC hashes
+fn random_salt() -> Result<SaltString> {
+    let mut salt = [0u8; 16];
+    rng().fill(&mut salt);
+    SaltString::encode_b64(&salt).map_err(|e| anyhow!("Failed to encode salt: {}", e))
+}
+
+// pwhash silently clamps the rounds into range, reject them instead
+fn crypt_setup(costs: &HashCosts) -> Result<HashSetup<'static>> {
+    if !CRYPT_ROUNDS.contains(&costs.crypt_rounds) {
+        return Err(anyhow!(
+            "SHA-crypt rounds must be between {} and {}",
+            CRYPT_ROUNDS.start(),
+            CRYPT_ROUNDS.end()
+        ));
     }
+
+    Ok(HashSetup {
+        salt: None,
+        rounds: Some(costs.crypt_rounds),
+    })
 }
 
 fn encode_htpasswd_bcrypt(password: &str, cost: u32) -> Result<String> {
@@ -42,3 +305,90 @@ fn encode_htpasswd_bcrypt(password: &str, cost: u32) -> Result<String> {
         .map(|hash| hash.to_string())
         .context("Failed to hash password")
 }
+
+/// Format an htpasswd entry for `username`. Fails on names htpasswd files cannot hold
+pub fn htpasswd_line(username: &str, hash: &str) -> Result<String> {
+    if username.is_empty() || username.contains([':', '\n', '\r']) {
+        return Err(anyhow!(
+            "Invalid htpasswd username '{}': it must be non-empty without ':' or newlines",
+            username
+        ));
+    }
+
+    Ok(format!("{}:{}", username, hash))
+}
+
+// Apache's variant of md5-crypt, which only differs in the magic string
+fn encode_htpasswd_apr1(password: &[u8], salt: &[u8]) -> String {
+    use md5::Digest as _;
+
+    const MAGIC: &[u8] = b"$apr1$";
+
+    let alternate = Md5::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(password)
+        .finalize();
+
+    let mut hasher = Md5::new()
+        .chain_update(password)
+        .chain_update(MAGIC)
+        .chain_update(salt);
+    for chunk in password.chunks(16) {
+        hasher.update(&alternate[..chunk.len()]);
+    }
+    let mut length = password.len();
+    while length > 0 {
+        if length & 1 == 1 {
+            hasher.update([0]);
+        } else {
+            hasher.update(&password[..1]);
+        }
+        length >>= 1;
+    }
+    let mut digest = hasher.finalize();
+
+    // 1000 extra rounds to slow down brute forcing
+    for round in 0..1000 {
+        let mut hasher = Md5::new();
+        if round & 1 == 1 {
+            hasher.update(password);
+        } else {
+            hasher.update(digest);
+        }
+        if round % 3 != 0 {
+            hasher.update(salt);
+        }
+        if round % 7 != 0 {
+            hasher.update(password);
+        }
+        if round & 1 == 1 {
+            hasher.update(digest);
+        } else {
+            hasher.update(password);
+        }
+        digest = hasher.finalize();
+    }
+
+    let mut hash = String::from_utf8_lossy(MAGIC).into_owned();
+    hash.push_str(&String::from_utf8_lossy(salt));
+    hash.push('$');
+
+    let groups = [(0, 6, 12), (1, 7, 13), (2, 8, 14), (3, 9, 15), (4, 10, 5)];
+    for (a, b, c) in groups {
+        let value =
+            (u32::from(digest[a]) << 16) | (u32::from(digest[b]) << 8) | u32::from(digest[c]);
+        push_crypt_base64(&mut hash, value, 4);
+    }
+    push_crypt_base64(&mut hash, u32::from(digest[11]), 2);
+
+    hash
+}
+
+// Least significant six bits first, as crypt(3) does
+fn push_crypt_base64(out: &mut String, mut value: u32, chars: usize) {
+    for _ in 0..chars {
+        out.push(char::from(CRYPT_ALPHABET[(value & 0x3f) as usize]));
+        value >>= 6;
+    }
+}
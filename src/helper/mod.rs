@@ -1,3 +1,3 @@
-pub mod phonetics;
-pub mod output;
-pub mod encoder;
\ No newline at end of file
+pub mod encoder;
+pub(crate) mod output;
+pub(crate) mod phonetics;
@@ -24,4 +24,3 @@ pub fn is_clean_break(word: &str, index: usize) -> bool {
 
     true
 }
-
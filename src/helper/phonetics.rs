@@ -4,10 +4,7 @@ pub fn ends_with_vowel(word: &str) -> bool {
     }
 
     let last_char = word.chars().last().unwrap().to_ascii_lowercase();
-    match last_char {
-        'a' | 'e' | 'i' | 'o' | 'u' | 'y' => true,
-        _ => false,
-    }
+    matches!(last_char, 'a' | 'e' | 'i' | 'o' | 'u' | 'y')
 }
 
 // Helper function to check if a string contains a vowel
@@ -20,14 +17,17 @@ pub fn contains_vowel(word: &str) -> bool {
     false
 }
 
-// Define phonetic patterns for different styles
-pub fn get_phonetics() -> (
+// Initial consonants, middle consonants, final consonants, vowels and endings
+pub type Phonetics = (
     Vec<&'static str>,
     Vec<&'static str>,
     Vec<&'static str>,
     Vec<&'static str>,
     Vec<&'static str>,
-) {
+);
+
+// Define phonetic patterns for different styles
+pub fn get_phonetics() -> Phonetics {
     // Initial consonants (word beginnings)
     let initial_consonants = vec![
         // Common single consonants (higher weight for common ones)
@@ -42,9 +42,8 @@ pub fn get_phonetics() -> (
     let middle_consonants = vec![
         // Single consonants (higher weight for common ones)
         "b", "c", "c", "d", "d", "f", "g", "k", "l", "l", "l", "m", "m", "n", "n", "n", "p", "r",
-        "r", "r", "s", "s", "s", "t", "t", "t", "v", "x", "z",
-        "bb", "cc", "dd", "ff", "gg", "ll", "mm", "nn", "pp", "rr", "ss", "tt",
-        // Common blends within words
+        "r", "r", "s", "s", "s", "t", "t", "t", "v", "x", "z", "bb", "cc", "dd", "ff", "gg", "ll",
+        "mm", "nn", "pp", "rr", "ss", "tt", // Common blends within words
         "ch", "ck", "ct", "ld", "lf", "lk", "ll", "lm", "ln", "lp", "lt", "mb", "mm", "mp", "nc",
         "nd", "ng", "nk", "nn", "nt", "ph", "pt", "rb", "rc", "rd", "rf", "rg", "rk", "rl", "rm",
         "rn", "rp", "rr", "rs", "rt", "rth", "sc", "sh", "sk", "sl", "sp", "ss", "st", "th",
@@ -85,4 +84,4 @@ pub fn get_phonetics() -> (
         vowels,
         endings,
     )
-}
\ No newline at end of file
+}
@@ -0,0 +1,46 @@
+//! Generate easy to remember passwords.
+//!
+//! Passwords are made of pronounceable synthesized words (or words from a [`Wordlist`]),
+//! numbers and special characters. [`PasswordGenerator`] ties the generators together with
+//! length bounds, casing and a minimum entropy, and every generated password reports the
+//! estimated entropy of the choices that produced it.
+//!
+//! ```
+//! use pw::PasswordGenerator;
+//! use rand::SeedableRng;
+//! use rand_chacha::ChaCha8Rng;
+//!
+//! let generator = PasswordGenerator::builder()
+//!     .pattern("W-W-N")
+//!     .capitalize(true)
+//!     .build()?;
+//!
+//! let mut rng = ChaCha8Rng::seed_from_u64(42);
+//! let generated = generator.generate(&mut rng)?;
+//! println!("{} ({:.1} bits)", generated.password, generated.entropy_bits);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! The individual generators implement [`Generator`] and the lower level functions in
+//! [`generator::password`] and [`generator::word`] can be used directly as well.
+//! Passwords can be encoded or hashed with [`encode_password`].
+
+pub mod generator;
+pub mod helper;
+pub mod models;
+
+pub use generator::analysis::analyze_password;
+pub use generator::builder::{PasswordGenerator, PasswordGeneratorBuilder};
+pub use generator::charset::{Charset, CharsetPreset};
+pub use generator::composition::Composition;
+pub use generator::derive::{DeriveInput, Kdf, derive_rng, derive_seed};
+pub use generator::pattern::Pattern;
+pub use generator::strategy::{
+    DefaultGenerator, Generator, PassphraseGenerator, PatternGenerator, RandomGenerator,
+};
+pub use generator::wordlist::{WordSource, Wordlist};
+pub use helper::encoder::{
+    EncodingFormat, HashCosts, encode_password, encode_password_with, htpasswd_line,
+};
+pub use models::generated_password::GeneratedPassword;
+pub use models::password_analysis::{PasswordAnalysis, Segment, SegmentKind};
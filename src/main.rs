@@ -1,112 +1,372 @@
 mod cli;
-mod generator;
-mod helper;
-mod models;
-
-use crate::cli::cli::{Cli, EncodingFormat, OutputFormat};
-use crate::generator::password::{
-    generate_password_with_target_length, generate_pattern_password, random_chars,
-    random_uppercase_char,
-};
-use crate::helper::encoder::encode_password;
-use crate::models::password_output::PasswordOutput;
-use anyhow::{Context, Result};
+
+use crate::cli::cli::{Cli, Command, ConfigCommand, OutputFormat, PatternCommand};
+use crate::cli::output::{PasswordWriter, print};
+use crate::cli::template::Layout;
+use crate::cli::{check, config, explain};
+use anyhow::{Context, Result, anyhow};
 use clap::error::ErrorKind;
-use clap::{CommandFactory, Parser};
-use rand::SeedableRng;
-use rand_chacha::ChaCha8Rng;
-use rand_chacha::rand_core::TryRngCore;
+use clap::{CommandFactory, ValueEnum};
+use pw::generator::entropy::round_bits;
+use pw::models::password_output::PasswordOutput;
+use pw::{
+    Charset, Composition, DeriveInput, EncodingFormat, HashCosts, PasswordGenerator, WordSource,
+    Wordlist, derive_rng, encode_password_with, htpasswd_line,
+};
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::{ChaCha8Rng, ChaCha20Rng};
+use std::collections::BTreeMap;
+
 fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let (mut cli, sources) = config::resolve(&matches)?;
+
+    // An explained pattern takes the place of -p, so it is read with the same options
+    if let Some(Command::Pattern(PatternCommand::Explain { expression })) = &cli.command {
+        cli.pattern = Some(expression.clone());
+    }
+
+    match &cli.command {
+        Some(Command::Config(ConfigCommand::Show)) => return config::show(&cli, &sources),
+        Some(Command::Check { passwords }) => return check::run(&cli, passwords),
+        Some(Command::Pattern(PatternCommand::Explain { .. })) => {
+            command_validation(&cli);
+            return explain::run(&cli, &build_generator(&cli)?);
+        }
+        Some(Command::Derive { .. }) | None => {}
+    }
+
     command_validation(&cli);
 
-    let mut rng = match cli.seed {
+    let generator = build_generator(&cli)?;
+
+    // Casing modifiers used to be literal text, strict patterns point them out
+    if cli.strict_pattern {
+        for warning in generator.modifier_warnings() {
+            eprintln!("Warning: {}", warning);
+        }
+    }
+
+    if let Some(Command::Derive {
+        site,
+        user,
+        counter,
+        kdf,
+    }) = &cli.command
+    {
+        let master = rpassword::prompt_password("Master passphrase: ")
+            .context("Failed to read the master passphrase")?;
+        if master.is_empty() {
+            return Err(anyhow!("The master passphrase cannot be empty"));
+        }
+
+        let input = DeriveInput {
+            site,
+            user,
+            counter: *counter,
+        };
+        let mut rng = derive_rng(&master, &input, *kdf)?;
+        return generate_passwords(&cli, &generator, &mut rng);
+    }
+
+    // A 64 bit seed caps the randomness of every password at 64 bits, so it is only used when
+    // reproducible output is asked for. Otherwise ChaCha20 gets a full 256 bit seed from the OS
+    match cli.seed {
         Some(seed) => {
             if cli.verbose {
-                println!("Seed: {}", seed);
+                print(&format!("Seed: {}\n", seed))?;
             }
-            ChaCha8Rng::seed_from_u64(seed)
-        },
+            generate_passwords(&cli, &generator, &mut ChaCha8Rng::seed_from_u64(seed))
+        }
         None => {
-            let random_seed = ChaCha8Rng::from_os_rng()
-                .try_next_u64()
-                .context("Failed to generate random seed")?;
-            if cli.verbose {
-                println!("Seed: {}", random_seed);
-            }
-            ChaCha8Rng::seed_from_u64(random_seed)
+            let mut rng =
+                ChaCha20Rng::try_from_os_rng().context("Failed to seed the random generator")?;
+            generate_passwords(&cli, &generator, &mut rng)
         }
-    };
+    }
+}
 
-    let mut min_length = cli.min_length.unwrap_or(8);
-    let mut max_length = cli.max_length.unwrap_or(15);
-    if let Some(length) = cli.length {
-        min_length = length;
-        max_length = length;
+fn generate_passwords<R: RngCore + CryptoRng>(
+    cli: &Cli,
+    generator: &PasswordGenerator,
+    rng: &mut R,
+) -> Result<()> {
+    let costs = hash_costs(cli);
+    let layout = layout(cli)?;
+    let encodings = encodings(cli, layout.as_ref());
+    let hash_params: BTreeMap<String, BTreeMap<String, u32>> = encodings
+        .iter()
+        .filter_map(|format| {
+            let parameters = costs.parameters(format)?;
+            let parameters = parameters
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect();
+            Some((format.name().to_string(), parameters))
+        })
+        .collect();
+
+    if !hash_params.is_empty() {
+        costs.validate()?;
     }
 
     let num_passwords = cli.number_of_passwords;
-    let mut passwords = Vec::with_capacity(num_passwords);
+    let seed = cli.seed.map(|seed| seed.to_string()).unwrap_or_default();
 
-    for _ in 0..num_passwords {
-        let mut password = if cli.random {
-            random_chars(&mut rng, min_length, max_length)
-        } else if let Some(pattern) = &cli.pattern {
-            generate_pattern_password(&mut rng, pattern, min_length, max_length, cli.capitalize)?
-        } else {
-            generate_password_with_target_length(&mut rng, min_length, max_length, cli.capitalize)?
+    let hash_param_columns = encodings
+        .iter()
+        .flat_map(|format| {
+            let parameters = costs.parameters(format).unwrap_or_default();
+            parameters
+                .into_iter()
+                .map(|(parameter, _)| (format.name(), parameter))
+        })
+        .collect();
+    // Unique passwords are generated up front, so running out of them fails before any output
+    let mut unique = if cli.unique {
+        generator.generate_unique(rng, num_passwords)?
+    } else {
+        Vec::new()
+    }
+    .into_iter();
+
+    let mut writer = PasswordWriter::new(
+        &cli.output,
+        &encodings,
+        hash_param_columns,
+        cli.username.is_some(),
+    )?;
+
+    if let Some(header) = layout.as_ref().and_then(|layout| layout.header.as_ref()) {
+        let values = BTreeMap::from([("name", cli.name.clone()), ("seed", seed.clone())]);
+        print(&format!("{}\n", header.render(&values)))?;
+    }
+
+    for index in 1..=num_passwords {
+        let generated = match unique.next() {
+            Some(generated) => generated,
+            None => generator.generate(rng)?,
         };
 
-        if !cli.random {
-            if !cli.capitalize && !cli.lowercase {
-                password = random_uppercase_char(&password, &mut rng);
+        let password = generated.password;
+        let entropy_bits = round_bits(generated.entropy_bits);
+
+        let mut encoded = BTreeMap::new();
+        for format in &encodings {
+            let mut value = encode_password_with(&password, format, &costs)?;
+            if let Some(username) = &cli.username
+                && format.is_htpasswd()
+            {
+                value = htpasswd_line(username, &value)?;
             }
+            encoded.insert(format.name().to_string(), value);
         }
 
-        if cli.lowercase {
-            password = password.to_lowercase();
-        }
+        if writer.is_none() {
+            if let Some(layout) = &layout {
+                let key = if num_passwords > 1 {
+                    format!("{}_{}", cli.name, index)
+                } else {
+                    cli.name.clone()
+                };
 
-        if matches!(cli.format, OutputFormat::Text) {
-            println!("{}", password);
-        } else {
-            let encoded = match &cli.encode {
-                EncodingFormat::None => None,
-                encoding_format => Some(encode_password(&password, encoding_format)?),
-            };
+                let mut values: BTreeMap<&str, String> = encoded
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.clone()))
+                    .collect();
+                values.insert("password", password);
+                values.insert("index", index.to_string());
+                values.insert("seed", seed.clone());
+                values.insert("entropy", entropy_bits.to_string());
+                values.insert("name", cli.name.clone());
+                values.insert("key", key);
 
+                let rendered = layout.item.render(&values);
+                if layout.final_newline {
+                    print(&format!("{}\n", rendered))?;
+                } else {
+                    print(&rendered)?;
+                }
+            } else if htpasswd_entries(cli, &encodings) {
+                for format in &encodings {
+                    print(&format!("{}\n", encoded[format.name()]))?;
+                }
+            } else if !encodings.is_empty() {
+                // One tab separated column per encoding, in the order they were asked for
+                let mut columns = vec![password.as_str()];
+                columns.extend(
+                    encodings
+                        .iter()
+                        .map(|format| encoded[format.name()].as_str()),
+                );
+                if cli.verbose {
+                    print(&format!("{}\t{}\n", columns.join("\t"), entropy_bits))?;
+                } else {
+                    print(&format!("{}\n", columns.join("\t")))?;
+                }
+            } else if cli.verbose {
+                print(&format!(
+                    "{} ({} bits of entropy)\n",
+                    password, entropy_bits
+                ))?;
+            } else {
+                print(&format!("{}\n", password))?;
+            }
+        } else {
             let output = PasswordOutput {
                 decoded: password,
                 encoded,
+                entropy_bits,
+                hash_params: hash_params.clone(),
+                username: cli.username.clone(),
             };
 
-            passwords.push(output);
+            if let Some(writer) = &mut writer {
+                writer.write(index, &output)?;
+            }
         }
     }
 
-    // Output structured formats if needed
-    match cli.format {
-        OutputFormat::Json => {
-            let json = serde_json::to_string_pretty(&passwords)?;
-            println!("{}", json);
-        }
-        OutputFormat::Yaml => {
-            let yaml = serde_yml::to_string(&passwords)?;
-            println!("{}", yaml);
-        }
-        _ => {}
+    if let Some(writer) = writer {
+        writer.finish()?;
     }
 
     Ok(())
 }
 
+// The requested encodings followed by the ones the template refers to, without duplicates.
+// `none` only stands for no encoding
+fn encodings(cli: &Cli, layout: Option<&Layout>) -> Vec<EncodingFormat> {
+    let referenced = layout
+        .into_iter()
+        .flat_map(|layout| layout.item.placeholders())
+        .filter_map(|name| EncodingFormat::from_str(name, false).ok());
+
+    let mut encodings = Vec::new();
+    for format in cli.encode.iter().cloned().chain(referenced) {
+        if format != EncodingFormat::None && !encodings.contains(&format) {
+            encodings.push(format);
+        }
+    }
+    encodings
+}
+
+// Text output of only htpasswd encodings with a username is a ready to append htpasswd file
+fn htpasswd_entries(cli: &Cli, encodings: &[EncodingFormat]) -> bool {
+    cli.username.is_some() && encodings.iter().all(EncodingFormat::is_htpasswd)
+}
+
+// Placeholders available to every password in a template
+const PLACEHOLDERS: &[&str] = &["password", "index", "seed", "entropy", "name", "key"];
+
+fn layout(cli: &Cli) -> Result<Option<Layout>> {
+    let Some(template) = &cli.template else {
+        return Ok(None);
+    };
+
+    let known =
+        |name: &str| PLACEHOLDERS.contains(&name) || EncodingFormat::from_str(name, false).is_ok();
+    Layout::parse(template, known).map(Some)
+}
+
+fn hash_costs(cli: &Cli) -> HashCosts {
+    HashCosts {
+        argon2_memory_kib: cli.argon2_memory,
+        argon2_iterations: cli.argon2_iterations,
+        argon2_parallelism: cli.argon2_parallelism,
+        scrypt_log_n: cli.scrypt_log_n,
+        scrypt_r: cli.scrypt_r,
+        scrypt_p: cli.scrypt_p,
+        pbkdf2_rounds: cli.pbkdf2_rounds,
+        crypt_rounds: cli.crypt_rounds,
+        bcrypt_cost: cli.bcrypt_cost,
+    }
+}
+
+fn build_generator(cli: &Cli) -> Result<PasswordGenerator> {
+    let charset = Charset::from_options(
+        &cli.preset,
+        cli.symbols.as_deref(),
+        cli.exclude_chars.as_deref(),
+    )?;
+
+    let mut builder = PasswordGenerator::builder()
+        .charset(charset)
+        .composition(Composition {
+            upper: cli.require_upper,
+            lower: cli.require_lower,
+            digit: cli.require_digit,
+            symbol: cli.require_symbol,
+        })
+        .capitalize(cli.capitalize)
+        .lowercase(cli.lowercase)
+        .separator(cli.separator.clone())
+        .inject_digit(cli.inject_digit)
+        .inject_symbol(cli.inject_symbol)
+        .strict_pattern(cli.strict_pattern);
+
+    if cli.random {
+        builder = builder.random();
+    } else if let Some(word_count) = cli.passphrase {
+        builder = builder.passphrase(word_count);
+    } else if let Some(pattern) = &cli.pattern {
+        builder = builder.pattern(pattern.clone());
+    }
+
+    if let Some(wordlist) = &cli.wordlist {
+        builder = builder.word_source(WordSource::List(Wordlist::load(wordlist)?));
+    }
+    if let Some(length) = cli.length {
+        builder = builder.length(length);
+    }
+    if let Some(min_length) = cli.min_length {
+        builder = builder.min_length(min_length);
+    }
+    if let Some(max_length) = cli.max_length {
+        builder = builder.max_length(max_length);
+    }
+    if let Some(min_entropy) = cli.min_entropy {
+        builder = builder.min_entropy(min_entropy);
+    }
+
+    builder.build()
+}
+
 fn command_validation(cli: &Cli) {
     let mut cmd = Cli::command();
 
-    if matches!(cli.format, OutputFormat::Text) && !matches!(cli.encode, EncodingFormat::None) {
+    let layout = match layout(cli) {
+        Ok(layout) => layout,
+        Err(e) => cmd.error(ErrorKind::ValueValidation, e).exit(),
+    };
+    let encodings = encodings(cli, layout.as_ref());
+
+    if let Some(layout) = &layout
+        && !layout.multiple
+        && cli.number_of_passwords > 1
+    {
+        cmd.error(
+            ErrorKind::ValueValidation,
+            format!(
+                "The {} template holds a single password",
+                cli.template.as_deref().unwrap_or_default()
+            ),
+        )
+        .exit();
+    }
+
+    if cli.username.is_some() && !encodings.iter().any(EncodingFormat::is_htpasswd) {
+        cmd.error(
+            ErrorKind::MissingRequiredArgument,
+            "--username needs an htpasswd encoding\n\nUse -e htpasswd, -e htpasswd-sha or -e htpasswd-apr1",
+        )
+        .exit();
+    }
+
+    if cli.template.is_some() && !matches!(cli.output, OutputFormat::Text) {
         cmd.error(
             ErrorKind::ArgumentConflict,
-            "Encoding options (-e) cannot be used with text output format (-O text)",
+            "--template only applies to text output (-O text)",
         )
         .exit();
     }
@@ -136,6 +396,69 @@ fn command_validation(cli: &Cli) {
         .exit();
     }
 
+    if cli.passphrase.is_some() && (cli.random || cli.pattern.is_some()) {
+        cmd.error(
+            ErrorKind::ArgumentConflict,
+            "Cannot combine passphrase with random or pattern",
+        )
+        .exit();
+    }
+
+    if cli.passphrase == Some(0) {
+        cmd.error(
+            ErrorKind::ValueValidation,
+            "A passphrase needs at least one word",
+        )
+        .exit();
+    }
+
+    if cli.wordlist.is_some() && cli.passphrase.is_none() && cli.pattern.is_none() {
+        cmd.error(
+            ErrorKind::ArgumentConflict,
+            "--wordlist can only be used with --passphrase or --pattern",
+        )
+        .exit();
+    }
+
+    if cli.passphrase.is_none() && (cli.inject_digit || cli.inject_symbol) {
+        cmd.error(
+            ErrorKind::ArgumentConflict,
+            "--inject-digit and --inject-symbol can only be used with --passphrase",
+        )
+        .exit();
+    }
+
+    if cli.lowercase && cli.require_upper > 0 {
+        cmd.error(
+            ErrorKind::ArgumentConflict,
+            "--require-upper cannot be used with --lowercase",
+        )
+        .exit();
+    }
+
+    if let Some(Command::Derive { site, .. }) = &cli.command {
+        if cli.seed.is_some() {
+            cmd.error(
+                ErrorKind::ArgumentConflict,
+                "--seed cannot be used with derive, the seed comes from the master passphrase",
+            )
+            .exit();
+        }
+
+        if site.trim().is_empty() {
+            cmd.error(ErrorKind::ValueValidation, "--site cannot be empty")
+                .exit();
+        }
+    }
+
+    if cli.min_entropy.is_some_and(|bits| bits < 0.0) {
+        cmd.error(
+            ErrorKind::ValueValidation,
+            "Minimum entropy cannot be negative",
+        )
+        .exit();
+    }
+
     if cli.min_length.is_some() && cli.min_length.unwrap() < 1 {
         cmd.error(
             ErrorKind::ValueValidation,
@@ -143,4 +466,9 @@ fn command_validation(cli: &Cli) {
         )
         .exit();
     }
+
+    if cli.length == Some(0) {
+        cmd.error(ErrorKind::ValueValidation, "Length must be greater than 0")
+            .exit();
+    }
 }
@@ -0,0 +1,15 @@
+/// A generated password together with the estimated entropy of the choices that produced it
+#[derive(Debug, Clone)]
+pub struct GeneratedPassword {
+    pub password: String,
+    pub entropy_bits: f64,
+}
+
+impl GeneratedPassword {
+    pub fn new(password: String, entropy_bits: f64) -> Self {
+        Self {
+            password,
+            entropy_bits,
+        }
+    }
+}
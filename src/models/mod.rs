@@ -1,2 +1,6 @@
+pub mod generated_password;
+pub mod password_analysis;
+pub mod password_check;
+pub mod password_output;
+pub mod pattern_explanation;
 pub mod word;
-pub mod password_output;
\ No newline at end of file
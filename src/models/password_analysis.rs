@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+/// What kind of pattern token a part of a password corresponds to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SegmentKind {
+    Word,
+    Number,
+    Symbol,
+    Literal,
+}
+
+/// A run of characters of the same kind, e.g. `hello` or `42`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Segment {
+    pub kind: SegmentKind,
+    pub text: String,
+}
+
+/// The makeup of an existing password
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordAnalysis {
+    pub password: String,
+    pub length: usize,
+    pub uppercase: usize,
+    pub lowercase: usize,
+    pub digits: usize,
+    pub symbols: usize,
+    pub entropy_bits: f64,
+    /// The pattern that produces passwords of the same shape, e.g. `W-N-C`
+    pub pattern: String,
+    pub segments: Vec<Segment>,
+}
@@ -0,0 +1,13 @@
+use crate::models::password_analysis::PasswordAnalysis;
+use serde::{Deserialize, Serialize};
+
+/// The analysis of a checked password and whether it meets the policy it was checked against
+#[derive(Serialize, Deserialize)]
+pub struct PasswordCheck {
+    #[serde(flatten)]
+    pub analysis: PasswordAnalysis,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matches_policy: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub policy_violations: Vec<String>,
+}
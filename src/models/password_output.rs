@@ -1,7 +1,17 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Serialize, Deserialize)]
 pub struct PasswordOutput {
     pub decoded: String,
-    pub encoded: Option<String>,
-}
\ No newline at end of file
+    /// Encoded forms of the password, keyed by encoding name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub encoded: BTreeMap<String, String>,
+    pub entropy_bits: f64,
+    /// Cost parameters of the hashes in `encoded`, only for slow password hashes
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hash_params: BTreeMap<String, BTreeMap<String, u32>>,
+    /// htpasswd user the htpasswd entries in `encoded` are for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+}
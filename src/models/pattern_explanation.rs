@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+/// Range of the length and estimated entropy of what a pattern or part of it produces
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Bounds {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub min_entropy_bits: f64,
+    pub max_entropy_bits: f64,
+}
+
+impl Bounds {
+    /// Fixed text of this many characters
+    pub fn fixed(length: usize) -> Self {
+        Self {
+            min_length: length,
+            max_length: length,
+            min_entropy_bits: 0.0,
+            max_entropy_bits: 0.0,
+        }
+    }
+
+    /// One part followed by the other
+    pub fn then(self, other: Bounds) -> Self {
+        Self {
+            min_length: self.min_length + other.min_length,
+            max_length: self.max_length.saturating_add(other.max_length),
+            min_entropy_bits: self.min_entropy_bits + other.min_entropy_bits,
+            max_entropy_bits: self.max_entropy_bits + other.max_entropy_bits,
+        }
+    }
+
+    /// Either of the two
+    pub fn or(self, other: Bounds) -> Self {
+        Self {
+            min_length: self.min_length.min(other.min_length),
+            max_length: self.max_length.max(other.max_length),
+            min_entropy_bits: self.min_entropy_bits.min(other.min_entropy_bits),
+            max_entropy_bits: self.max_entropy_bits.max(other.max_entropy_bits),
+        }
+    }
+}
+
+/// What a single element of a pattern produces, e.g. `W[4-6]{2}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenExplanation {
+    /// The element as written in a pattern
+    pub token: String,
+    /// The possible outputs, e.g. `word of 4 to 6 letters, 2 times`
+    pub output: String,
+    #[serde(flatten)]
+    pub bounds: Bounds,
+    /// The alternatives of a group, or the elements of a group with a single alternative
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TokenExplanation>,
+}
+
+/// A parsed pattern with what each of its elements produces, see `pw pattern explain`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternExplanation {
+    pub pattern: String,
+    #[serde(flatten)]
+    pub bounds: Bounds,
+    pub tokens: Vec<TokenExplanation>,
+    /// Likely mistakes, such as letters that are literal text instead of tokens
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
@@ -1,8 +1,7 @@
 use serde::Serialize;
 
-#[derive(Serialize, Clone)]
-#[derive(Debug)]
+#[derive(Serialize, Clone, Debug)]
 pub struct Word {
     pub word: String,
     pub length: usize,
-}
\ No newline at end of file
+}